@@ -0,0 +1,155 @@
+use alloc::vec::Vec;
+
+use core::ops::{Add, Bound, RangeBounds, Sub};
+
+/// Point add and range sum in O(log n), uses n elements of memory
+///
+/// We can not ensure this requirments with Rust
+/// (1) a + default = a
+/// (2) (a + b) + c = a + (b + c)
+/// (3) (a + b) - b = a
+pub struct FenwickTree<T>
+where
+    T: Copy + Default + Add<Output = T> + Sub<Output = T>,
+{
+    /// data[i - 1] is the sum of the elements in (i - lowbit(i), i]
+    data: Vec<T>,
+}
+
+impl<T> FenwickTree<T>
+where
+    T: Copy + Default + Add<Output = T> + Sub<Output = T>,
+{
+    pub fn new() -> Self {
+        Self {
+            data: Vec::new(),
+        }
+    }
+
+    /// O(n), every partial sum is pushed once to its parent
+    pub fn build(values: &[T]) -> Self {
+        let mut data = values.to_vec();
+        for i in 1..=data.len() {
+            let parent = i + lowbit(i);
+            if parent <= data.len() {
+                data[parent - 1] = data[parent - 1] + data[i - 1];
+            }
+        }
+
+        Self { data }
+    }
+
+    pub fn add(&mut self, index: usize, delta: T) {
+        assert!(index < self.len(), "index {index} is out of bounds for length {}", self.len());
+
+        let mut i = index + 1;
+        while i <= self.len() {
+            self.data[i - 1] = self.data[i - 1] + delta;
+            i += lowbit(i);
+        }
+    }
+
+    /// index inclusive
+    pub fn prefix_sum(&self, index: usize) -> T {
+        assert!(index < self.len(), "index {index} is out of bounds for length {}", self.len());
+        self.sum_before(index + 1)
+    }
+
+    /// left inclusive
+    /// right inclusive
+    pub fn range_sum(&self, left: usize, right: usize) -> T {
+        assert!(left <= right, "range {left}..={right} is empty");
+        self.prefix_sum(right) - self.sum_before(left)
+    }
+
+    /// Empty ranges sum to default
+    pub fn query_range<R: RangeBounds<usize>>(&self, range: R) -> T {
+        let start = match range.start_bound() {
+            Bound::Included(start) => *start,
+            Bound::Excluded(before_start) => *before_start + 1,
+            Bound::Unbounded => 0,
+        };
+
+        let end = match range.end_bound() {
+            Bound::Included(end) => *end + 1,
+            Bound::Excluded(after_end) => *after_end,
+            Bound::Unbounded => self.len(),
+        };
+
+        assert!(end <= self.len(), "range end {end} is out of bounds for length {}", self.len());
+        if start >= end {
+            T::default()
+        } else {
+            self.sum_before(end) - self.sum_before(start)
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Sum of the first count elements
+    fn sum_before(&self, mut count: usize) -> T {
+        let mut res = T::default();
+        while count > 0 {
+            res = res + self.data[count - 1];
+            count -= lowbit(count);
+        }
+
+        res
+    }
+}
+
+impl<T> Default for FenwickTree<T>
+where
+    T: Copy + Default + Add<Output = T> + Sub<Output = T>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[inline]
+const fn lowbit(i: usize) -> usize {
+    i & i.wrapping_neg()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn fenwick_tree() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let mut values: Vec<i64> = (0..123).map(|_| rng.gen_range(-100..100)).collect();
+        let mut fenwick = FenwickTree::build(&values);
+
+        for _ in 0..1_000 {
+            let index = rng.gen_range(0..values.len());
+            let delta = rng.gen_range(-100..100);
+            fenwick.add(index, delta);
+            values[index] += delta;
+
+            let index = rng.gen_range(0..values.len());
+            assert_eq!(fenwick.prefix_sum(index), values[..=index].iter().sum::<i64>());
+
+            let l = rng.gen_range(0..values.len());
+            let r = rng.gen_range(l..values.len());
+            let expected: i64 = values[l..=r].iter().sum();
+            assert_eq!(fenwick.range_sum(l, r), expected, "l: {l}, r: {r}");
+            assert_eq!(fenwick.query_range(l..=r), expected, "l: {l}, r: {r}");
+        }
+
+        assert_eq!(fenwick.query_range(..), values.iter().sum::<i64>());
+        assert_eq!(fenwick.query_range(5..5), 0);
+        assert!(FenwickTree::<i64>::new().is_empty());
+    }
+}
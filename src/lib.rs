@@ -1,4 +1,59 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+pub mod fenwick;
 pub mod segment_tree;
 pub mod sqrt_decomposition;
 pub mod treap;
 
+
+/// Only uses what is left without std, run with --no-default-features
+#[cfg(test)]
+mod tests {
+    use crate::segment_tree::SegmentTree;
+    use crate::treap::{ImplicitTreap, Treap};
+    use core::ops::Add;
+    use rand::rngs::SmallRng;
+
+    #[test]
+    fn without_std() {
+        let mut segtree = SegmentTree::build(&[1, 2, 3, 4], <u32 as Add>::add, 0);
+        segtree.assign_single(2, 10);
+        assert_eq!(segtree.query(1, 3), 16);
+
+        let mut treap = Treap::<u32, SmallRng>::from_seed([1; 32]);
+        assert!(treap.insert(5));
+        assert!(treap.insert(3));
+        assert_eq!(treap.split(&4).len(), 1);
+        assert_eq!(treap.len(), 1);
+
+        let mut implicit = ImplicitTreap::<u32, SmallRng>::from_seed([2; 32]);
+        implicit.insert_before(0, 7);
+        implicit.insert_before(0, 6);
+        assert_eq!(implicit.get(1), Some(&7));
+    }
+
+    /// segment_tree is the only segment tree module, these are reachable from the public path
+    #[test]
+    fn segment_tree_public_surface() {
+        let values = [5, 1, 4, 2];
+
+        let mut lazy = SegmentTree::build(&values, |a: i32, b: i32| a.max(b), i32::MIN)
+            .with_lazy(|value, delta| value + delta, 0);
+        lazy.modify(1, 2, 10);
+        assert_eq!(lazy.query(0, 3), 14);
+
+        let mut assignment = SegmentTree::build(&values, <i32 as Add>::add, 0)
+            .with_assignment(|value, len| value * len as i32);
+        assignment.assign(0, 2, 3);
+        assert_eq!(assignment.query(0, 3), 11);
+
+        let mut segtree = SegmentTree::build(&values, <i32 as Add>::add, 0);
+        let mut iter = segtree.iter();
+        assert_eq!(iter.value(), &12);
+        assert_eq!(iter.left(), Some(&6));
+        segtree.assign_single(0, 0);
+        assert_eq!(segtree.query_range(..), 7);
+    }
+}
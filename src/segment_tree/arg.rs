@@ -0,0 +1,158 @@
+use alloc::vec::Vec;
+
+use core::ops::RangeBounds;
+
+use super::SegmentTree;
+
+/// Extreme value of a segment and its smallest index, None for no elements
+type Extreme<T> = Option<(T, usize)>;
+type ExtremeFn<T> = fn(Extreme<T>, Extreme<T>) -> Extreme<T>;
+
+/// Finds the extreme value of a range and the smallest index attaining it,
+/// use ArgMinSegmentTree or ArgMaxSegmentTree
+pub struct ArgSegmentTree<T: Copy + Ord, const MAX: bool> {
+    tree: SegmentTree<Extreme<T>, ExtremeFn<T>>,
+}
+
+pub type ArgMinSegmentTree<T> = ArgSegmentTree<T, false>;
+pub type ArgMaxSegmentTree<T> = ArgSegmentTree<T, true>;
+
+impl<T: Copy + Ord, const MAX: bool> ArgSegmentTree<T, MAX> {
+    pub fn build(values: &[T]) -> Self {
+        let values: Vec<Extreme<T>> = values.iter()
+            .enumerate()
+            .map(|(index, &value)| Some((value, index)))
+            .collect();
+
+        Self {
+            tree: SegmentTree::build(&values, merge::<T, MAX>, None),
+        }
+    }
+
+    pub fn assign_single(&mut self, position: usize, value: T) {
+        self.tree.assign_single(position, Some((value, position)))
+    }
+
+    pub fn query_range<R: RangeBounds<usize>>(&self, range: R) -> (T, usize) {
+        let (left, right) = self.tree.range_into_segment(range);
+        self.query(left, right)
+    }
+
+    /// Returns the extreme value and the smallest index attaining it
+    pub fn query(&self, left: usize, right: usize) -> (T, usize) {
+        assert!(left <= right, "range {left}..={right} is empty");
+        self.tree.query(left, right).unwrap()
+    }
+
+    /// Same as query, but index first and None for an empty range,
+    /// ties go to the smallest index as well
+    pub fn query_arg(&self, left: usize, right: usize) -> Option<(usize, T)> {
+        if left > right {
+            return None;
+        }
+
+        self.tree.query(left, right).map(|(value, index)| (index, value))
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+}
+
+/// Ties go to left, which is the one with the smaller index
+fn merge<T: Copy + Ord, const MAX: bool>(left: Extreme<T>, right: Extreme<T>) -> Extreme<T> {
+    match (left, right) {
+        (Some((left_value, _)), Some((right_value, _))) => {
+            let right_wins = if MAX { right_value > left_value } else { right_value < left_value };
+            if right_wins { right } else { left }
+        }
+        (None, _) => right,
+        (_, None) => left,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    fn arg_extreme(values: &[u8], left: usize, right: usize, max: bool) -> (u8, usize) {
+        let value = if max {
+            *values[left..=right].iter().max().unwrap()
+        } else {
+            *values[left..=right].iter().min().unwrap()
+        };
+
+        let index = left + values[left..=right].iter().position(|&x| x == value).unwrap();
+        (value, index)
+    }
+
+    #[test]
+    fn arg_min_max() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let mut values: Vec<u8> = (0..77).map(|_| rng.gen_range(0..4)).collect();
+        let mut arg_min = ArgMinSegmentTree::build(&values);
+        let mut arg_max = ArgMaxSegmentTree::build(&values);
+
+        for _ in 0..1_000 {
+            if rng.gen_bool(0.3) {
+                let position = rng.gen_range(0..values.len());
+                let value = rng.gen_range(0..4);
+                arg_min.assign_single(position, value);
+                arg_max.assign_single(position, value);
+                values[position] = value;
+            }
+
+            let l = rng.gen_range(0..values.len());
+            let r = rng.gen_range(l..values.len());
+            assert_eq!(arg_min.query(l, r), arg_extreme(&values, l, r, false), "l: {l}, r: {r}");
+            assert_eq!(arg_max.query(l, r), arg_extreme(&values, l, r, true), "l: {l}, r: {r}");
+        }
+
+        let arg_min = ArgMinSegmentTree::build(&[3, 1, 2, 1, 1]);
+        assert_eq!(arg_min.query_arg(2, 4), Some((3, 1)));
+        assert_eq!(arg_min.query_arg(3, 2), None);
+        assert_eq!(arg_min.query_range(..), (1, 1));
+        assert_eq!(arg_min.query_range(2..), (1, 3));
+    }
+
+    #[test]
+    #[should_panic(expected = "range 0..0 is empty, length is 0")]
+    fn query_range_empty() {
+        ArgMaxSegmentTree::<u8>::build(&[]).query_range(..);
+    }
+
+    #[test]
+    fn query_arg_points_at_value() {
+        let mut rng = SmallRng::seed_from_u64(2);
+
+        for len in 1..40 {
+            let values: Vec<i32> = (0..len).map(|_| rng.gen_range(-3..3)).collect();
+            let arg_min = ArgMinSegmentTree::build(&values);
+            let arg_max = ArgMaxSegmentTree::build(&values);
+
+            for l in 0..len {
+                for r in l..len {
+                    let (index, value) = arg_min.query_arg(l, r).unwrap();
+                    assert!((l..=r).contains(&index));
+                    assert_eq!(values[index], value);
+                    assert!(values[l..index].iter().all(|&x| x > value));
+                    assert!(values[index..=r].iter().all(|&x| x >= value));
+
+                    let (index, value) = arg_max.query_arg(l, r).unwrap();
+                    assert!((l..=r).contains(&index));
+                    assert_eq!(values[index], value);
+                    assert!(values[l..index].iter().all(|&x| x < value));
+                    assert!(values[index..=r].iter().all(|&x| x <= value));
+                }
+            }
+        }
+    }
+}
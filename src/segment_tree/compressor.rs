@@ -0,0 +1,122 @@
+use alloc::vec::Vec;
+
+use core::ops::{Bound, Range, RangeBounds};
+
+/// Maps sorted distinct keys to 0..len
+#[derive(Clone, Debug, Default)]
+pub struct Compressor<K: Ord> {
+    keys: Vec<K>,
+}
+
+impl<K: Ord> Compressor<K> {
+    pub fn new<I: IntoIterator<Item = K>>(keys: I) -> Self {
+        let mut keys: Vec<K> = keys.into_iter().collect();
+        keys.sort();
+        keys.dedup();
+        Self { keys }
+    }
+
+    /// Keys must be sorted and distinct
+    pub(super) fn from_sorted(keys: Vec<K>) -> Self {
+        debug_assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+        Self { keys }
+    }
+
+    pub fn index_of(&self, key: &K) -> Option<usize> {
+        self.keys.binary_search(key).ok()
+    }
+
+    /// Panics if index is out of range
+    #[inline]
+    pub fn key_of(&self, index: usize) -> &K {
+        &self.keys[index]
+    }
+
+    /// Indices of the compressed keys lying in range, the result may be empty,
+    /// keys of range need not be compressed themselves
+    pub fn range_to_indices<R: RangeBounds<K>>(&self, range: R) -> Range<usize> {
+        let start = match range.start_bound() {
+            Bound::Included(start) => self.keys.partition_point(|key| key < start),
+            Bound::Excluded(start) => self.keys.partition_point(|key| key <= start),
+            Bound::Unbounded => 0,
+        };
+
+        let end = match range.end_bound() {
+            Bound::Included(end) => self.keys.partition_point(|key| key <= end),
+            Bound::Excluded(end) => self.keys.partition_point(|key| key < end),
+            Bound::Unbounded => self.keys.len(),
+        };
+
+        start..end.max(start)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+impl<K: Ord> FromIterator<K> for Compressor<K> {
+    fn from_iter<I: IntoIterator<Item = K>>(keys: I) -> Self {
+        Self::new(keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::segment_tree::SegmentTree;
+    use std::ops::Add;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn compressor() {
+        let compressor: Compressor<i64> = [30, -10, 20, 30, 0].into_iter().collect();
+
+        assert_eq!(compressor.len(), 4);
+        assert_eq!(compressor.index_of(&20), Some(2));
+        assert_eq!(compressor.index_of(&25), None);
+        assert_eq!(*compressor.key_of(0), -10);
+        assert_eq!(compressor.range_to_indices(-5..=25), 1..3);
+        assert_eq!(compressor.range_to_indices(0..30), 1..3);
+        assert_eq!(compressor.range_to_indices(21..29), 3..3);
+        assert_eq!(compressor.range_to_indices(..), 0..4);
+    }
+
+    #[test]
+    fn build_compressed() {
+        const MAX: i64 = 1_000_000_000_000_000_000;
+
+        let mut rng = SmallRng::seed_from_u64(1);
+        let mut points: Vec<(i64, i64)> = (0..300)
+            .map(|_| (rng.gen_range(-MAX..=MAX), rng.gen_range(-1_000..1_000)))
+            .collect();
+        points.extend_from_within(..50);
+
+        let (compressor, segtree) = SegmentTree::build_compressed(points.clone(), <i64 as Add>::add, 0);
+        assert_eq!(compressor.len(), 300);
+
+        for _ in 0..1_000 {
+            let mut left = rng.gen_range(-MAX..=MAX);
+            let mut right = rng.gen_range(-MAX..=MAX);
+            if left > right {
+                std::mem::swap(&mut left, &mut right);
+            }
+
+            let expected = points.iter()
+                .filter(|(key, _)| (left..=right).contains(key))
+                .map(|(_, value)| value)
+                .sum::<i64>();
+
+            let indices = compressor.range_to_indices(left..=right);
+            let sum = if indices.is_empty() { 0 } else { segtree.query_range(indices) };
+            assert_eq!(sum, expected, "left: {left}, right: {right}");
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use alloc::vec::Vec;
+
+use core::ops::{Add, RangeBounds};
+
+use super::{AssignmentSegmentTree, SegmentTree};
+
+type CountFn = fn(usize, usize) -> usize;
+
+/// Range assignment and the number of elements equal to target in a range,
+/// a leaf is 1 if it equals target, so assigning target gives a segment its length
+pub struct CountEqualSegmentTree<T: PartialEq> {
+    counts: AssignmentSegmentTree<usize, CountFn, CountFn>,
+    target: T,
+}
+
+impl<T: PartialEq> CountEqualSegmentTree<T> {
+    pub fn new(values: &[T], target: T) -> Self {
+        let counts: Vec<usize> = values.iter()
+            .map(|value| usize::from(*value == target))
+            .collect();
+
+        Self {
+            counts: SegmentTree::build(&counts, <usize as Add>::add as CountFn, 0).with_sum_assignment(),
+            target,
+        }
+    }
+
+    pub fn assign_single(&mut self, position: usize, value: T) {
+        let count = self.count_of(&value);
+        self.counts.assign_single(position, count);
+    }
+
+    pub fn assign_range<R: RangeBounds<usize>>(&mut self, range: R, value: T) {
+        let count = self.count_of(&value);
+        self.counts.assign_range(range, count);
+    }
+
+    /// left inclusive
+    /// right inclusive
+    pub fn assign(&mut self, left: usize, right: usize, value: T) {
+        let count = self.count_of(&value);
+        self.counts.assign(left, right, count);
+    }
+
+    pub fn count_equal_range<R: RangeBounds<usize>>(&mut self, range: R) -> usize {
+        self.counts.query_range(range)
+    }
+
+    /// left inclusive
+    /// right inclusive
+    /// Gives 0 when left > right
+    pub fn count_equal(&mut self, left: usize, right: usize) -> usize {
+        self.counts.query(left, right)
+    }
+
+    #[inline]
+    pub fn target(&self) -> &T {
+        &self.target
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    #[inline]
+    fn count_of(&self, value: &T) -> usize {
+        usize::from(*value == self.target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn count_equal() {
+        let mut rng = SmallRng::seed_from_u64(1);
+
+        for len in [1, 6, 33, 64] {
+            let mut values: Vec<u8> = (0..len).map(|_| rng.gen_range(0..3)).collect();
+            let mut segtree = CountEqualSegmentTree::new(&values, 1);
+
+            for _ in 0..500 {
+                let l = rng.gen_range(0..len);
+                let r = rng.gen_range(l..len);
+
+                match rng.gen_range(0..3) {
+                    0 => {
+                        let value = rng.gen_range(0..3);
+                        segtree.assign(l, r, value);
+                        values[l..=r].fill(value);
+                    }
+                    1 => {
+                        let value = rng.gen_range(0..3);
+                        segtree.assign_single(l, value);
+                        values[l] = value;
+                    }
+                    _ => {
+                        let expected = values[l..=r].iter().filter(|&&value| value == 1).count();
+                        assert_eq!(segtree.count_equal(l, r), expected, "len: {len}, l: {l}, r: {r}");
+                    }
+                }
+            }
+        }
+
+        let mut segtree = CountEqualSegmentTree::new(&["a", "b", "a", "c"], "a");
+        assert_eq!(segtree.count_equal_range(..), 2);
+        segtree.assign_range(1..3, "a");
+        assert_eq!(segtree.count_equal_range(..), 3);
+        segtree.assign_range(0..2, "c");
+        assert_eq!(segtree.count_equal_range(..), 1);
+        assert_eq!(segtree.count_equal_range(0..2), 0);
+        assert_eq!(*segtree.target(), "a");
+    }
+}
@@ -0,0 +1,200 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use core::ops::Range;
+
+/// Nodes are allocated on first touch, so memory is proportional
+/// to the number of touched positions, not to the size of the range
+///
+/// We can not ensure this requirments with Rust
+/// (1) merge(a, neutral) = a
+/// (2) merge(merge(a, b), c) = merge(a, merge(b, c))
+pub struct DynamicSegmentTree<T, M>
+where
+    T: Copy,
+    M: Fn(T, T) -> T,
+{
+    nodes: Vec<Node<T>>,
+    range: Range<u64>,
+    merge_fn: M,
+    neutral: T,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Node<T> {
+    value: T,
+    left: u32,
+    right: u32,
+}
+
+impl<T, M> DynamicSegmentTree<T, M>
+where
+    T: Copy,
+    M: Fn(T, T) -> T,
+{
+    /// Node 0 stands for every untouched subtree
+    const EMPTY: u32 = 0;
+    const ROOT: u32 = 1;
+
+    pub fn new(range: Range<u64>, merge_fn: M, neutral: T) -> Self {
+        assert!(range.start < range.end, "range {range:?} is empty");
+
+        let empty = Node { value: neutral, left: Self::EMPTY, right: Self::EMPTY };
+
+        Self {
+            nodes: vec![empty, empty],
+            range,
+            merge_fn,
+            neutral,
+        }
+    }
+
+    pub fn assign(&mut self, position: u64, value: T) {
+        self.update(position, |_| value)
+    }
+
+    /// Replaces the value at position with f(value)
+    pub fn update<F: FnOnce(T) -> T>(&mut self, position: u64, f: F) {
+        assert!(self.range.contains(&position), "position {position} is out of range {:?}", self.range);
+
+        let mut path = Vec::with_capacity(64);
+        let mut vertex = Self::ROOT;
+        let (mut left, mut right) = (self.range.start, self.range.end);
+
+        while right - left > 1 {
+            path.push(vertex);
+            let middle = left + (right - left) / 2;
+
+            vertex = if position < middle {
+                right = middle;
+                self.child_or_insert(vertex, true)
+            } else {
+                left = middle;
+                self.child_or_insert(vertex, false)
+            };
+        }
+
+        let leaf = &mut self.nodes[vertex as usize];
+        leaf.value = f(leaf.value);
+
+        for vertex in path.into_iter().rev() {
+            let Node { left, right, .. } = self.nodes[vertex as usize];
+            self.nodes[vertex as usize].value = self.merge(
+                self.nodes[left as usize].value,
+                self.nodes[right as usize].value,
+            );
+        }
+    }
+
+    /// left inclusive
+    /// right inclusive
+    pub fn query(&self, left: u64, right: u64) -> T {
+        if left > right {
+            return self.neutral;
+        }
+
+        self.query_node(Self::ROOT, self.range.start, self.range.end, left, right)
+    }
+
+    /// Number of allocated nodes
+    #[inline]
+    pub fn nodes(&self) -> usize {
+        self.nodes.len() - 1
+    }
+
+    /// Segment of the node is [left, right), query is [from, to]
+    fn query_node(&self, vertex: u32, left: u64, right: u64, from: u64, to: u64) -> T {
+        if vertex == Self::EMPTY || to < left || right <= from {
+            return self.neutral;
+        }
+
+        let node = &self.nodes[vertex as usize];
+
+        if from <= left && right - 1 <= to {
+            return node.value;
+        }
+
+        let middle = left + (right - left) / 2;
+        self.merge(
+            self.query_node(node.left, left, middle, from, to),
+            self.query_node(node.right, middle, right, from, to),
+        )
+    }
+
+    fn child_or_insert(&mut self, vertex: u32, is_left: bool) -> u32 {
+        let node = &self.nodes[vertex as usize];
+        let child = if is_left { node.left } else { node.right };
+
+        if child != Self::EMPTY {
+            return child;
+        }
+
+        let child = u32::try_from(self.nodes.len()).expect("too many nodes");
+        self.nodes.push(Node { value: self.neutral, left: Self::EMPTY, right: Self::EMPTY });
+
+        let node = &mut self.nodes[vertex as usize];
+        if is_left {
+            node.left = child;
+        } else {
+            node.right = child;
+        }
+
+        child
+    }
+
+    #[inline]
+    fn merge(&self, left: T, right: T) -> T {
+        (self.merge_fn)(left, right)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::ops::Add;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn dynamic_segment_tree() {
+        const RANGE: Range<u64> = 1_000..1_000_000_001_000;
+
+        let mut rng = SmallRng::seed_from_u64(1);
+        let mut tree = DynamicSegmentTree::new(RANGE, <i64 as Add>::add, 0);
+        let mut values = BTreeMap::new();
+        let mut positions = Vec::new();
+
+        for _ in 0..2_000 {
+            let position = if !positions.is_empty() && rng.gen_bool(0.3) {
+                positions[rng.gen_range(0..positions.len())]
+            } else {
+                rng.gen_range(RANGE)
+            };
+            positions.push(position);
+
+            if rng.gen_bool(0.5) {
+                let value = rng.gen_range(-1_000..1_000);
+                tree.assign(position, value);
+                values.insert(position, value);
+            } else {
+                let delta = rng.gen_range(-1_000..1_000);
+                tree.update(position, |value| value + delta);
+                *values.entry(position).or_insert(0) += delta;
+            }
+
+            let mut left = rng.gen_range(RANGE);
+            let mut right = rng.gen_range(RANGE);
+            if left > right {
+                std::mem::swap(&mut left, &mut right);
+            }
+
+            assert_eq!(tree.query(left, right), values.range(left..=right).map(|(_, v)| v).sum::<i64>());
+        }
+
+        let first = *values.keys().next().unwrap();
+        assert_eq!(tree.query(first, first), values[&first]);
+        assert_eq!(tree.query(RANGE.start, RANGE.end - 1), values.values().sum::<i64>());
+        assert!(tree.nodes() <= values.len() * 40);
+    }
+}
@@ -0,0 +1,73 @@
+use core::fmt;
+use core::ops::{Bound, RangeBounds};
+
+/// Returned by the try_* methods of the segment trees
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RangeError {
+    OutOfBounds { position: usize, len: usize },
+    /// start >= end, bounds are half-open
+    Empty { start: usize, end: usize, len: usize },
+}
+
+impl fmt::Display for RangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfBounds { position, len } => {
+                write!(f, "position {position} is out of bounds for length {len}")
+            }
+            Self::Empty { start, end, len } => {
+                write!(f, "range {start}..{end} is empty, length is {len}")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RangeError {}
+
+pub(super) fn check_position(position: usize, len: usize) -> Result<(), RangeError> {
+    if position < len {
+        Ok(())
+    } else {
+        Err(RangeError::OutOfBounds { position, len })
+    }
+}
+
+/// left inclusive
+/// right inclusive
+pub(super) fn check_segment(left: usize, right: usize, len: usize) -> Result<(), RangeError> {
+    check_position(right, len)?;
+
+    if left > right {
+        Err(RangeError::Empty { start: left, end: right + 1, len })
+    } else {
+        Ok(())
+    }
+}
+
+/// Turns a range into a segment with both ends inclusive, never overflows
+pub(super) fn try_range_into_segment<R: RangeBounds<usize>>(range: R, len: usize) -> Result<(usize, usize), RangeError> {
+    let start = match range.start_bound() {
+        Bound::Included(start) => *start,
+        Bound::Excluded(before_start) => before_start.checked_add(1)
+            .ok_or(RangeError::Empty { start: usize::MAX, end: usize::MAX, len })?,
+        Bound::Unbounded => 0,
+    };
+
+    let end = match range.end_bound() {
+        Bound::Included(end) => end.checked_add(1)
+            .ok_or(RangeError::OutOfBounds { position: usize::MAX, len })?,
+        Bound::Excluded(after_end) => *after_end,
+        Bound::Unbounded => len,
+    };
+
+    if end > len {
+        return Err(RangeError::OutOfBounds { position: end - 1, len });
+    }
+
+    if start >= end {
+        return Err(RangeError::Empty { start, end, len });
+    }
+
+    Ok((start, end - 1))
+}
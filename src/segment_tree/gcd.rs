@@ -0,0 +1,127 @@
+use alloc::vec::Vec;
+
+use crate::fenwick::FenwickTree;
+
+use super::SegmentTree;
+
+pub type GcdFn = fn(i64, i64) -> i64;
+
+/// Range add and range gcd, gcd(a[l..=r]) = gcd(a[l], a[l + 1] - a[l], ..., a[r] - a[r - 1]),
+/// so a range add changes only two differences and a[l] is a prefix sum of them
+pub struct RangeAddGcd {
+    /// Prefix sums of diffs are the values
+    values: FenwickTree<i64>,
+    /// diffs[0] = a[0], diffs[i] = a[i] - a[i - 1]
+    diffs: SegmentTree<i64, GcdFn>,
+}
+
+impl SegmentTree<i64, GcdFn> {
+    /// Gcd of absolute values with point updates, gcd of no elements is 0
+    pub fn range_gcd(values: &[i64]) -> Self {
+        SegmentTree::build(values, gcd, 0)
+    }
+}
+
+impl RangeAddGcd {
+    pub fn new(values: &[i64]) -> Self {
+        let diffs: Vec<i64> = values.iter()
+            .scan(0, |last, &value| Some(value - core::mem::replace(last, value)))
+            .collect();
+
+        Self {
+            values: FenwickTree::build(&diffs),
+            diffs: SegmentTree::range_gcd(&diffs),
+        }
+    }
+
+    /// left inclusive
+    /// right inclusive
+    pub fn range_add(&mut self, left: usize, right: usize, delta: i64) {
+        assert!(left <= right, "range {left}..={right} is empty");
+        assert!(right < self.len(), "index {right} is out of bounds for length {}", self.len());
+
+        self.add_to_diff(left, delta);
+        if right + 1 < self.len() {
+            self.add_to_diff(right + 1, -delta);
+        }
+    }
+
+    /// left inclusive
+    /// right inclusive
+    /// Gcd of absolute values, 0 if all of them are 0
+    pub fn query_gcd(&self, left: usize, right: usize) -> i64 {
+        assert!(left <= right, "range {left}..={right} is empty");
+
+        let first = self.values.prefix_sum(left);
+        gcd(first, self.diffs.query(left + 1, right))
+    }
+
+    #[inline]
+    pub fn get(&self, index: usize) -> i64 {
+        self.values.prefix_sum(index)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    fn add_to_diff(&mut self, index: usize, delta: i64) {
+        self.values.add(index, delta);
+        self.diffs.assign_single(index, self.values.range_sum(index, index));
+    }
+}
+
+fn gcd(left: i64, right: i64) -> i64 {
+    let (mut a, mut b) = (left.unsigned_abs(), right.unsigned_abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+
+    i64::try_from(a).expect("gcd does not fit in i64")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    fn brute_force(values: &[i64]) -> i64 {
+        values.iter().fold(0, |res, &value| gcd(res, value))
+    }
+
+    #[test]
+    fn range_add_gcd() {
+        let mut rng = SmallRng::seed_from_u64(1);
+
+        for len in [1, 2, 9, 40] {
+            let mut values: Vec<i64> = (0..len).map(|_| 6 * rng.gen_range(-20..20)).collect();
+            let mut tree = RangeAddGcd::new(&values);
+
+            for _ in 0..500 {
+                let l = rng.gen_range(0..len);
+                let r = rng.gen_range(l..len);
+
+                if rng.gen_bool(0.4) {
+                    let delta = 6 * rng.gen_range(-5..5) + if rng.gen_bool(0.1) { 1 } else { 0 };
+                    tree.range_add(l, r, delta);
+                    values[l..=r].iter_mut().for_each(|value| *value += delta);
+                } else {
+                    assert_eq!(tree.query_gcd(l, r), brute_force(&values[l..=r]), "len: {len}, l: {l}, r: {r}");
+                    assert_eq!(tree.get(l), values[l]);
+                }
+            }
+        }
+
+        let segtree = SegmentTree::range_gcd(&[-12, 18, 0, 8]);
+        assert_eq!(segtree.query(0, 1), 6);
+        assert_eq!(segtree.query_range(..), 2);
+        assert_eq!(segtree.query(2, 2), 0);
+    }
+}
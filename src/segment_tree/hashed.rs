@@ -0,0 +1,135 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use core::convert::Infallible;
+use core::str::FromStr;
+
+use super::SegmentTree;
+
+/// Polynomial hashes of a segment modulo both moduli, power is base^len
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HashNode {
+    hash: [u64; 2],
+    power: [u64; 2],
+}
+
+type HashMerge = Box<dyn Fn(HashNode, HashNode) -> HashNode>;
+
+/// Substring hashes with point updates, every node is hashed
+/// with two (base, modulus) pairs to make collisions unlikely
+pub struct HashedSegmentTree {
+    tree: SegmentTree<HashNode, HashMerge>,
+    pairs: [(u64, u64); 2],
+}
+
+impl HashedSegmentTree {
+    /// Primes below 2^32, so the two hashes fit together in a u64
+    pub const DEFAULT_PAIRS: [(u64, u64); 2] = [(131, 1_000_000_007), (137, 998_244_353)];
+
+    /// Every pair is (base, modulus), moduli must be below 2^32
+    pub fn new(bytes: &[u8], pairs: [(u64, u64); 2]) -> Self {
+        for (base, modulus) in pairs {
+            assert!(modulus <= u32::MAX as u64, "modulus {modulus} does not fit in u32");
+            assert!(0 < base && base < modulus, "base {base} is not in 1..{modulus}");
+        }
+
+        let moduli = pairs.map(|(_, modulus)| modulus);
+        let merge: HashMerge = Box::new(move |left: HashNode, right: HashNode| HashNode {
+            hash: [0, 1].map(|i| (left.hash[i] * right.power[i] + right.hash[i]) % moduli[i]),
+            power: [0, 1].map(|i| left.power[i] * right.power[i] % moduli[i]),
+        });
+
+        let neutral = HashNode { hash: [0, 0], power: [1, 1] };
+        let mut res = Self {
+            tree: SegmentTree::new(merge, neutral),
+            pairs,
+        };
+
+        let leaves: Vec<HashNode> = bytes.iter().map(|&byte| res.leaf(byte)).collect();
+        res.tree.init_with(&leaves);
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        Self::new(bytes, Self::DEFAULT_PAIRS)
+    }
+
+    pub fn set_char(&mut self, position: usize, byte: u8) {
+        let leaf = self.leaf(byte);
+        self.tree.assign_single(position, leaf);
+    }
+
+    /// left inclusive
+    /// right inclusive
+    /// Equal substrings have equal hashes
+    pub fn hash(&self, left: usize, right: usize) -> u64 {
+        let node = self.tree.query(left, right);
+        (node.hash[0] << 32) | node.hash[1]
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Bytes are shifted by one, so that zero bytes change the hash
+    fn leaf(&self, byte: u8) -> HashNode {
+        HashNode {
+            hash: self.pairs.map(|(_, modulus)| (byte as u64 + 1) % modulus),
+            power: self.pairs.map(|(base, _)| base),
+        }
+    }
+}
+
+impl FromStr for HashedSegmentTree {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Infallible> {
+        Ok(Self::from_bytes(s.as_bytes()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn substring_hashes() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let mut bytes: Vec<u8> = (0..60).map(|_| rng.gen_range(b'a'..=b'b')).collect();
+        let mut tree = HashedSegmentTree::from_bytes(&bytes);
+
+        for _ in 0..200 {
+            let position = rng.gen_range(0..bytes.len());
+            let byte = rng.gen_range(b'a'..=b'b');
+            tree.set_char(position, byte);
+            bytes[position] = byte;
+
+            let len = rng.gen_range(1..=10);
+            for _ in 0..20 {
+                let first = rng.gen_range(0..=bytes.len() - len);
+                let second = rng.gen_range(0..=bytes.len() - len);
+
+                let equal = bytes[first..first + len] == bytes[second..second + len];
+                let equal_hashes = tree.hash(first, first + len - 1) == tree.hash(second, second + len - 1);
+                assert_eq!(equal, equal_hashes, "first: {first}, second: {second}, len: {len}");
+            }
+        }
+    }
+
+    #[test]
+    fn from_str() {
+        let tree: HashedSegmentTree = "abcabc".parse().unwrap();
+
+        assert_eq!(tree.len(), 6);
+        assert_eq!(tree.hash(0, 2), tree.hash(3, 5));
+        assert_ne!(tree.hash(0, 1), tree.hash(1, 2));
+    }
+}
@@ -1,5 +1,7 @@
 use super::SegmentTree;
 
+use core::slice;
+
 pub struct Iter<'a, T, M> 
 where
     T: Copy,
@@ -9,6 +11,12 @@ where
     vertex: usize,
 }
 
+/// Leaf values of a SegmentTree in positional order
+#[derive(Clone, Debug)]
+pub struct Leaves<'a, T> {
+    inner: slice::Iter<'a, T>,
+}
+
 impl<'a, T, M> Iter<'a, T, M> 
 where
     T: Copy,
@@ -22,6 +30,12 @@ where
             vertex: Self::START_VERTEX,
         }
     }
+
+    /// Moves back to the root
+    #[inline]
+    pub fn reset(&mut self) {
+        self.vertex = Self::START_VERTEX;
+    }
     
     #[inline]
     pub fn is_leaf(&self) -> bool {
@@ -33,6 +47,23 @@ where
         self.vertex.checked_sub(self.tree.len())
     }
 
+    /// Value at the current vertex
+    #[inline]
+    pub fn value(&self) -> &T {
+        &self.tree.data[self.vertex]
+    }
+
+    /// Moves to the other child of the parent
+    pub fn sibling(&mut self) -> Option<&T> {
+        match self.vertex {
+            Self::START_VERTEX => None,
+            _ => {
+                self.vertex ^= 1;
+                self.tree.data.get(self.vertex)
+            }
+        }
+    }
+
     pub fn parent(&mut self) -> Option<&T> {
         match self.vertex {
             Self::START_VERTEX => None, 
@@ -62,6 +93,51 @@ where
     }
 }
 
+/// Manual impl, so that neither T nor M has to be Clone
+impl<'a, T, M> Clone for Iter<'a, T, M> 
+where
+    T: Copy,
+    M: Fn(T, T) -> T,
+{
+    fn clone(&self) -> Self {
+        Self {
+            tree: self.tree,
+            vertex: self.vertex,
+        }
+    }
+}
+
+impl<'a, T> Leaves<'a, T> {
+    pub(super) fn new(leaves: &'a [T]) -> Self {
+        Self {
+            inner: leaves.iter(),
+        }
+    }
+}
+
+impl<'a, T> Iterator for Leaves<'a, T> {
+    type Item = &'a T;
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a T> {
+        self.inner.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Leaves<'a, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<&'a T> {
+        self.inner.next_back()
+    }
+}
+
+impl<'a, T> ExactSizeIterator for Leaves<'a, T> {}
+
 #[inline]
 const fn left_child(vertex: usize) -> usize {
     vertex << 1
@@ -119,4 +195,65 @@ mod tests {
 
         assert_eq!(&ans[..6], &[vec![5,0],vec![7,0],vec![5,2],vec![6,1],vec![4,4],vec![7,1]]);
     }
+
+    #[test]
+    fn value_and_sibling() {
+        let values = [1, 2, 3, 4, 5, 6, 7, 8];
+        let segtree = SegmentTree::build(&values, <u32 as Add>::add, 0);
+        let mut iter = segtree.iter();
+
+        assert_eq!(*iter.value(), 36);
+        assert_eq!(iter.sibling(), None);
+
+        assert_eq!(iter.right(), Some(&26));
+        assert_eq!(iter.sibling(), Some(&10));
+        assert_eq!(iter.right(), Some(&7));
+        assert_eq!(iter.left(), Some(&3));
+        assert_eq!(iter.sibling(), Some(&4));
+        assert!(iter.is_leaf());
+        assert_eq!(iter.index(), Some(3));
+
+        let mut path = vec![*iter.value()];
+        while iter.parent().is_some() {
+            path.push(*iter.value());
+        }
+        assert_eq!(path, [4, 7, 10, 36]);
+    }
+
+    #[test]
+    fn reset_and_clone() {
+        let values = [1, 2, 3, 4, 5, 6, 7, 8];
+        let merge: Box<dyn Fn(u32, u32) -> u32> = Box::new(|a, b| a + b);
+        let segtree = SegmentTree::build(&values, merge, 0);
+        let mut iter = segtree.iter();
+
+        assert_eq!(iter.left(), Some(&10));
+        assert_eq!(iter.left(), Some(&3));
+
+        let mut fork = iter.clone();
+        assert_eq!(fork.right(), Some(&2));
+        assert_eq!(iter.left(), Some(&1));
+        assert_eq!(fork.index(), Some(1));
+        assert_eq!(iter.index(), Some(0));
+
+        iter.reset();
+        assert_eq!(*iter.value(), 36);
+        assert_eq!(iter.index(), None);
+        assert_eq!(iter.right(), Some(&26));
+        assert_eq!(iter.right(), Some(&15));
+        assert_eq!(iter.left(), Some(&7));
+        assert_eq!(iter.index(), Some(6));
+        assert_eq!(*fork.value(), 2);
+    }
+
+    #[test]
+    fn leaves() {
+        let values = [4, 8, 15, 16, 23, 42];
+        let segtree = SegmentTree::build(&values, <u32 as Add>::add, 0);
+
+        assert_eq!(segtree.leaves().len(), 6);
+        assert_eq!(segtree.leaves().rev().copied().collect::<Vec<_>>(), [42, 23, 16, 15, 8, 4]);
+        assert_eq!(segtree.leaves().position(|&x| x == 16), Some(3));
+        assert!(segtree.leaves().zip(&values).all(|(a, b)| a == b));
+    }
 }
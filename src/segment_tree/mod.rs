@@ -1,12 +1,40 @@
-/// ---------------------------------------------------
-/// Inspired by https://codeforces.com/blog/entry/18051
-/// ---------------------------------------------------
-
+// ---------------------------------------------------
+// Inspired by https://codeforces.com/blog/entry/18051
+// ---------------------------------------------------
+
+mod arg;
+mod compressor;
+mod count;
+mod dynamic;
+mod error;
+mod gcd;
+mod hashed;
 mod iter;
-
-pub use iter::Iter;
-
-use std::ops::{RangeBounds, Bound};
+#[cfg(feature = "rayon")]
+mod parallel;
+mod persistent;
+mod rollback;
+mod subarray;
+
+pub use arg::{ArgSegmentTree, ArgMinSegmentTree, ArgMaxSegmentTree};
+pub use compressor::Compressor;
+pub use count::CountEqualSegmentTree;
+pub use dynamic::DynamicSegmentTree;
+pub use error::RangeError;
+pub use gcd::{RangeAddGcd, GcdFn};
+pub use hashed::{HashedSegmentTree, HashNode};
+pub use iter::{Iter, Leaves};
+pub use persistent::{PersistentSegmentTree, VersionId};
+pub use rollback::Checkpoint;
+pub use subarray::{MaxSubarray, MaxSubarrayNode, MaxSubarrayFn};
+
+use error::{check_position, check_segment, try_range_into_segment};
+use rollback::UndoLog;
+
+use alloc::collections::BinaryHeap;
+use alloc::vec::Vec;
+
+use core::ops::{RangeBounds, Mul};
 
 /// We can not ensure this requirments with Rust
 /// (1) merge(a, neutral) = a
@@ -19,11 +47,14 @@ where
     data: Vec<T>,
     merge_fn: M,
     neutral: T,
+    undo: UndoLog<T>,
 }
 
 
+/// Leaves are padded to a power of two, so merge may be non-commutative
+///
 /// We can not ensure this requirments with Rust
-/// (1) lazy(a, neutral) = a
+/// (1) lazy(lazy(a, b), c) = lazy(a, lazy(b, c))
 /// (2) lazy(merge(a, b), c) = merge(lazy(a, c), lazy(b, c))
 pub struct LazySegmentTree<T, M, L> 
 where
@@ -32,23 +63,58 @@ where
     L: Fn(T, T) -> T,
 {
     tree: SegmentTree<T, M>,
-    delayed: Vec<T>,
+    delayed: Vec<Option<T>>,
+    len: usize,
     lazy_fn: L,
-    neutral: T,
 }
 
 
+/// Leaves are padded to a power of two, so merge may be non-commutative
+/// Range add is available after with_add
+///
 /// We can not ensure this requirments with Rust
 /// (1) segment(a, k) = merge(merge(..., a), a) {k times}
-pub struct AssignmentSegmentTree<T, M, S> 
+pub struct AssignmentSegmentTree<T, M, S, A = fn(T, T, usize) -> T> 
 where
     T: Copy,
     M: Fn(T, T) -> T,
     S: Fn(T, usize) -> T,
+    A: Fn(T, T, usize) -> T,
 {
     tree: SegmentTree<T, M>,
-    delayed: Vec<Option<T>>,
+    delayed: Vec<Tag<T>>,
+    len: usize,
     segment_fn: S,
+    add_fn: Option<A>,
+}
+
+/// Pending operation of an AssignmentSegmentTree vertex
+#[derive(Clone, Copy, Debug)]
+enum Tag<T> {
+    Assign(T),
+    Add(T),
+    None,
+}
+
+/// Values have type T, pending updates have type U
+/// Leaves are padded to a power of two, so merge may be non-commutative
+///
+/// We can not ensure this requirments with Rust
+/// (1) apply(merge(a, b), u, k + m) = merge(apply(a, u, k), apply(b, u, m))
+/// (2) apply(apply(a, u, k), v, k) = apply(a, compose(u, v), k)
+pub struct LazyUpdateSegmentTree<T, U, M, A, C> 
+where
+    T: Copy,
+    U: Copy,
+    M: Fn(T, T) -> T,
+    A: Fn(T, U, usize) -> T,
+    C: Fn(U, U) -> U,
+{
+    tree: SegmentTree<T, M>,
+    delayed: Vec<Option<U>>,
+    len: usize,
+    apply_fn: A,
+    compose_fn: C,
 }
 
 impl<T, M> SegmentTree<T, M> 
@@ -63,6 +129,7 @@ where
             data: Vec::new(),
             merge_fn,
             neutral,
+            undo: UndoLog::default(),
         }
     }
 
@@ -72,9 +139,37 @@ where
         res
     }
 
+    /// Places values at the compressed positions of their keys,
+    /// values with equal keys are merged in the given order
+    pub fn build_compressed<K, I>(keys_and_values: I, merge: M, neutral: T) -> (Compressor<K>, Self) 
+    where
+        K: Ord,
+        I: IntoIterator<Item = (K, T)>,
+    {
+        let mut pairs: Vec<(K, T)> = keys_and_values.into_iter().collect();
+        pairs.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut keys: Vec<K> = Vec::with_capacity(pairs.len());
+        let mut values: Vec<T> = Vec::with_capacity(pairs.len());
+
+        for (key, value) in pairs {
+            match (keys.last(), values.last_mut()) {
+                (Some(last), Some(merged)) if *last == key => *merged = merge(*merged, value),
+                _ => {
+                    keys.push(key);
+                    values.push(value);
+                }
+            }
+        }
+
+        (Compressor::from_sorted(keys), Self::build(&values, merge, neutral))
+    }
+
+    /// Drops every checkpoint
     pub fn init_with(&mut self, values: &[T]) {
         let len = values.len();
 
+        self.undo.clear();
         self.data.clear();
         self.data.resize(len, self.neutral);
         self.data.extend_from_slice(values);
@@ -86,22 +181,131 @@ where
     }
     
     pub fn assign_single(&mut self, position: usize, value: T) {
+        check_position(position, self.len()).unwrap_or_else(|err| panic!("{err}"));
         let mut vertex = self.vertex_from_position(position);
-        self.data[vertex] = value;
+        self.set(vertex, value);
 
         while vertex > Self::START_VERTEX {
             vertex = parent(vertex);
-            let (left, right) = children(vertex);
-            self.data[vertex] = self.merge(self.data[left], self.data[right]); 
+            self.recalculate(vertex);
+        }
+    }
+
+    /// Old name of assign_single, kept for one release
+    #[deprecated(since = "0.1.0", note = "use assign_single")]
+    #[inline]
+    pub fn assign(&mut self, position: usize, value: T) {
+        self.assign_single(position, value)
+    }
+
+    pub fn try_assign_single(&mut self, position: usize, value: T) -> Result<(), RangeError> {
+        check_position(position, self.len())?;
+        self.assign_single(position, value);
+        Ok(())
+    }
+
+    /// Writes every leaf first, then recomputes only the affected vertices,
+    /// or all of them if that is cheaper, later updates of a position win
+    pub fn batch_assign(&mut self, updates: &[(usize, T)]) {
+        for &(position, _) in updates {
+            check_position(position, self.len()).unwrap_or_else(|err| panic!("{err}"));
+        }
+
+        for &(position, value) in updates {
+            let vertex = self.vertex_from_position(position);
+            self.set(vertex, value);
+        }
+
+        if self.rebuilds_all(updates.len()) {
+            for vertex in (Self::START_VERTEX..self.len()).rev() {
+                self.recalculate(vertex);
+            }
+            return;
+        }
+
+        // children have greater numbers than their parent, so taking vertices
+        // from the greatest recomputes each of them once, after its children
+        let mut vertices: BinaryHeap<usize> = updates.iter()
+            .map(|&(position, _)| parent(self.vertex_from_position(position)))
+            .collect();
+        let mut last = None;
+
+        while let Some(vertex) = vertices.pop() {
+            if last == Some(vertex) {
+                continue;
+            }
+
+            last = Some(vertex);
+            self.recalculate(vertex);
+            if vertex > Self::START_VERTEX {
+                vertices.push(parent(vertex));
+            }
+        }
+    }
+
+    /// Same as batch_assign
+    #[inline]
+    pub fn assign_many(&mut self, updates: &[(usize, T)]) {
+        self.batch_assign(updates)
+    }
+
+    /// Replaces the value at position with f(value)
+    pub fn update_single<F: FnOnce(T) -> T>(&mut self, position: usize, f: F) {
+        check_position(position, self.len()).unwrap_or_else(|err| panic!("{err}"));
+        let vertex = self.vertex_from_position(position);
+        self.assign_single(position, f(self.data[vertex]))
+    }
+
+    /// While a checkpoint is active, assign_single and update_single
+    /// record the values they overwrite, checkpoints may be nested
+    pub fn checkpoint(&mut self) -> Checkpoint {
+        self.undo.checkpoint()
+    }
+
+    /// Restores the values from the time checkpoint was made in time
+    /// proportional to the number of updates since then,
+    /// checkpoints made after it become invalid
+    pub fn rollback(&mut self, checkpoint: Checkpoint) {
+        for (vertex, value) in self.undo.rollback(checkpoint) {
+            self.data[vertex] = value;
         }
     }
     
+    /// Wraps around when left > right, e.g. (5, 1) on length 8 is 5, 6, 7, 0, 1
+    pub fn query_circular(&self, left: usize, right: usize) -> T {
+        if left <= right {
+            self.query(left, right)
+        } else {
+            self.merge(self.query(left, self.len() - 1), self.query(0, right))
+        }
+    }
+
+    /// Empty ranges give neutral
     pub fn query_range<R: RangeBounds<usize>>(&self, range: R) -> T {
-        let (left, right) = self.range_into_segment(range);
-        self.query(left, right)
+        match try_range_into_segment(range, self.len()) {
+            Ok((left, right)) => self.query(left, right),
+            Err(RangeError::Empty { .. }) => self.neutral,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    pub fn try_query_range<R: RangeBounds<usize>>(&self, range: R) -> Result<T, RangeError> {
+        let (left, right) = try_range_into_segment(range, self.len())?;
+        Ok(self.query(left, right))
     }
 
+    pub fn try_query(&self, left: usize, right: usize) -> Result<T, RangeError> {
+        check_segment(left, right, self.len())?;
+        Ok(self.query(left, right))
+    }
+
+    /// Gives neutral when left > right, e.g. query(i + 1, i)
     pub fn query(&self, left: usize, right: usize) -> T {
+        check_position(right, self.len()).unwrap_or_else(|err| panic!("{err}"));
+        if left > right {
+            return self.neutral;
+        }
+
         let mut left_res = self.neutral;
         let mut right_res = self.neutral;
         let mut left_vertex = self.vertex_from_position(left);
@@ -130,17 +334,58 @@ where
     // it when self.len() is power of 2
     pub fn iter(&self) -> Iter<'_, T, M> {
         assert!(self.len().is_power_of_two());
-        Iter::new(&self)
+        Iter::new(self)
+    }
+
+    /// Values of the leaves in positional order
+    pub fn leaves(&self) -> Leaves<'_, T> {
+        Leaves::new(&self.data[self.len()..])
+    }
+
+    /// Running merge of the leaves starting from neutral, one value per leaf,
+    /// the i-th is the same as query(0, i)
+    pub fn prefix_folds(&self) -> impl Iterator<Item = T> + '_ {
+        self.data[self.len()..].iter().scan(self.neutral, |res, &leaf| {
+            *res = self.merge(*res, leaf);
+            Some(*res)
+        })
     }
 
     pub fn with_assignment<S: Fn(T, usize) -> T>(self, segment_fn: S) -> AssignmentSegmentTree<T, M, S> {
         AssignmentSegmentTree::new(self, segment_fn)
     }
+
+    /// apply_fn(value, update, len) applies update to a segment of len elements,
+    /// compose_fn(first, second) is the update doing first, then second
+    pub fn with_update<U, A, C>(self, apply_fn: A, compose_fn: C) -> LazyUpdateSegmentTree<T, U, M, A, C> 
+    where
+        U: Copy,
+        A: Fn(T, U, usize) -> T,
+        C: Fn(U, U) -> U,
+    {
+        LazyUpdateSegmentTree::new(self, apply_fn, compose_fn)
+    }
+
+    #[inline]
+    pub fn neutral(&self) -> T {
+        self.neutral
+    }
+
+    /// Merges partial results the same way the tree does
+    #[inline]
+    pub fn merge_pub(&self, left: T, right: T) -> T {
+        self.merge(left, right)
+    }
  
     #[inline]
     pub fn len(&self) -> usize {
         self.data.len() >> 1
     }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
     
     #[inline]
     fn merge(&self, left: T, right: T) -> T {
@@ -152,64 +397,137 @@ where
         position + self.len()
     }
 
-    fn range_into_segment<R: RangeBounds<usize>>(&self, range: R) -> (usize, usize) {
-        let left = match range.start_bound() {
-            Bound::Included(start) => *start,
-            Bound::Excluded(before_start) => *before_start + 1,
-            _ => 0,
-        };
+    #[inline]
+    fn recalculate(&mut self, vertex: usize) {
+        let (left, right) = children(vertex);
+        self.set(vertex, self.merge(self.data[left], self.data[right]));
+    }
 
-        let right = match range.end_bound() {
-            Bound::Included(end) => *end,
-            Bound::Excluded(after_end) => *after_end - 1,
-            _ => self.len() - 1,
-        };
+    /// Whether a batch of updates is cheaper done by a full rebuild
+    #[inline]
+    fn rebuilds_all(&self, updates: usize) -> bool {
+        let height = (usize::BITS - self.len().leading_zeros()) as usize;
+        updates.saturating_mul(height) >= self.len()
+    }
+
+    #[inline]
+    fn set(&mut self, vertex: usize, value: T) {
+        self.undo.record(vertex, self.data[vertex]);
+        self.data[vertex] = value;
+    }
+
+    /// Panics on empty ranges too, for trees without a neutral answer
+    #[inline]
+    fn range_into_segment<R: RangeBounds<usize>>(&self, range: R) -> (usize, usize) {
+        try_range_into_segment(range, self.len()).unwrap_or_else(|err| panic!("{err}"))
+    }
 
-        (left, right)
+    /// Pads values with neutral up to a power of two, so every vertex
+    /// covers a contiguous segment and siblings are adjacent
+    fn init_padded(&mut self, values: &[T]) {
+        let mut padded = values.to_vec();
+        padded.resize(values.len().next_power_of_two(), self.neutral);
+        self.init_with(&padded);
     }
 }
 
 impl<T, M> SegmentTree<T, M> 
 where
-    T: Copy + Eq,
+    T: Copy,
     M: Fn(T, T) -> T,
 {
-    pub fn with_lazy<L: Fn(T, T) -> T>(self, lazy_fn: L, neutral: T) -> LazySegmentTree<T, M, L> {
-        LazySegmentTree::new(self, lazy_fn, neutral)
+    /// Pending values are stored as Option, so neutral is not used anymore
+    pub fn with_lazy<L: Fn(T, T) -> T>(self, lazy_fn: L, _neutral: T) -> LazySegmentTree<T, M, L> {
+        LazySegmentTree::new(self, lazy_fn)
+    }
+
+    /// lazy_fn(value, delayed, len) applies delayed to a segment of len elements,
+    /// pending values are composed with len = 1
+    ///
+    /// We can not ensure this requirments with Rust
+    /// (1) lazy(lazy(a, b, k), c, k) = lazy(a, lazy(b, c, 1), k)
+    /// (2) lazy(merge(a, b), c, k + m) = merge(lazy(a, c, k), lazy(b, c, m))
+    pub fn with_lazy_len<L>(self, lazy_fn: L) -> LazyUpdateSegmentTree<T, T, M, L, impl Fn(T, T) -> T> 
+    where
+        L: Fn(T, T, usize) -> T + Clone,
+    {
+        let compose_fn = lazy_fn.clone();
+        self.with_update(lazy_fn, move |first, second| compose_fn(first, second, 1))
     }
 }
 
 impl<T, M, L> LazySegmentTree<T, M, L> 
 where
-    T: Copy + Eq,
+    T: Copy,
     M: Fn(T, T) -> T,
     L: Fn(T, T) -> T,
 {
     const START_VERTEX: usize = SegmentTree::<T, M>::START_VERTEX;
 
-    pub fn new(tree: SegmentTree<T, M>, lazy_fn: L, neutral: T) -> Self {
-        let delayed = vec![neutral; tree.len()];
-
-        Self {
+    pub fn new(tree: SegmentTree<T, M>, lazy_fn: L) -> Self {
+        let mut res = Self {
             tree,
-            delayed,
+            delayed: Vec::new(),
+            len: 0,
             lazy_fn,
-            neutral,
-        }
+        };
+
+        let values = res.tree.data[res.tree.len()..].to_vec();
+        res.init_with(&values);
+        res
     }
    
     pub fn modify_single(&mut self, position: usize, value: T) {
+        check_position(position, self.len).unwrap_or_else(|err| panic!("{err}"));
         let vertex = self.tree.vertex_from_position(position);
         self.apply(vertex, value);
         self.build(vertex);
     }
+
+    /// Same as SegmentTree::batch_assign, pending values above
+    /// the updated positions are pushed down first
+    pub fn batch_assign(&mut self, updates: &[(usize, T)]) {
+        for &(position, _) in updates {
+            check_position(position, self.len).unwrap_or_else(|err| panic!("{err}"));
+        }
+
+        if self.tree.rebuilds_all(updates.len()) {
+            for vertex in Self::START_VERTEX..self.tree.len() {
+                self.push_children(vertex);
+            }
+        } else {
+            for &(position, _) in updates {
+                self.push(self.tree.vertex_from_position(position));
+            }
+        }
+
+        self.tree.batch_assign(updates);
+    }
  
+    /// Wraps around when left > right
+    pub fn modify_circular(&mut self, left: usize, right: usize, value: T) {
+        if left <= right {
+            self.modify(left, right, value)
+        } else {
+            self.modify(left, self.len - 1, value);
+            self.modify(0, right, value);
+        }
+    }
+
     pub fn modify_range<R: RangeBounds<usize>>(&mut self, range: R, value: T) {
-        let (left, right) = self.tree.range_into_segment(range);
-        self.modify(left, right, value)
+        if let Some((left, right)) = range_into_segment(range, self.len) {
+            self.modify(left, right, value);
+        }
+    }
+
+    pub fn try_modify(&mut self, left: usize, right: usize, value: T) -> Result<(), RangeError> {
+        check_segment(left, right, self.len)?;
+        self.modify(left, right, value);
+        Ok(())
     }
 
     pub fn modify(&mut self, left: usize, right: usize, value: T) {
+        check_position(right, self.len).unwrap_or_else(|err| panic!("{err}"));
         let mut left_vertex = self.tree.vertex_from_position(left);
         let mut right_vertex = self.tree.vertex_from_position(right + 1);
         
@@ -235,12 +553,31 @@ where
         self.build(right_subtree);
     }
    
+    /// Wraps around when left > right, e.g. (5, 1) on length 8 is 5, 6, 7, 0, 1
+    pub fn query_circular(&mut self, left: usize, right: usize) -> T {
+        if left <= right {
+            self.query(left, right)
+        } else {
+            let suffix = self.query(left, self.len - 1);
+            let prefix = self.query(0, right);
+            self.tree.merge(suffix, prefix)
+        }
+    }
+
     pub fn query_range<R: RangeBounds<usize>>(&mut self, range: R) -> T {
-        let (left, right) = self.tree.range_into_segment(range);
-        self.query(left, right)
+        match range_into_segment(range, self.len) {
+            Some((left, right)) => self.query(left, right),
+            None => self.tree.neutral,
+        }
     }
 
+    /// Gives neutral when left > right
     pub fn query(&mut self, left: usize, right: usize) -> T {
+        check_position(right, self.len).unwrap_or_else(|err| panic!("{err}"));
+        if left > right {
+            return self.tree.neutral;
+        }
+
         let left_vertex = self.tree.vertex_from_position(left);
         let right_vertex = self.tree.vertex_from_position(right + 1);
         
@@ -249,11 +586,88 @@ where
    
         self.tree.query(left, right)
     }   
+
+    /// Same as query, but applies the pending values on the way
+    /// instead of pushing them down, in O(log n)
+    ///
+    /// Pending values of ancestors are applied after those of descendants,
+    /// which is the order query pushes them in. Both assume that
+    /// lazy(lazy(a, b), c) = lazy(lazy(a, c), b), since modify
+    /// does not push older pending values down either
+    pub fn query_ref(&self, left: usize, right: usize) -> T {
+        check_position(right, self.len).unwrap_or_else(|err| panic!("{err}"));
+
+        let mut left_res: Option<T> = None;
+        let mut right_res: Option<T> = None;
+        let mut left_vertex = self.tree.vertex_from_position(left);
+        let mut right_vertex = self.tree.vertex_from_position(right + 1);
+
+        // every vertex folded into left_res lies under left_vertex - 1,
+        // every vertex folded into right_res lies under right_vertex
+        while left_vertex < right_vertex {
+            if (left_vertex & 1) == 1 {
+                let value = self.tree.data[left_vertex];
+                left_res = Some(left_res.map_or(value, |res| self.tree.merge(res, value)));
+                left_vertex += 1;
+            }
+            
+            if (right_vertex & 1) == 1 {
+                right_vertex -= 1;
+                let value = self.tree.data[right_vertex];
+                right_res = Some(right_res.map_or(value, |res| self.tree.merge(value, res)));
+            }
+
+            left_vertex = parent(left_vertex);
+            right_vertex = parent(right_vertex);
+            left_res = self.apply_pending(left_res, left_vertex - 1);
+            right_res = self.apply_pending(right_res, right_vertex);
+        }
+
+        let (mut left_ancestor, mut right_ancestor) = (left_vertex - 1, right_vertex);
+        while left_ancestor > Self::START_VERTEX || right_ancestor > Self::START_VERTEX {
+            left_ancestor = parent(left_ancestor);
+            right_ancestor = parent(right_ancestor);
+            left_res = self.apply_pending(left_res, left_ancestor);
+            right_res = self.apply_pending(right_res, right_ancestor);
+        }
+
+        match (left_res, right_res) {
+            (Some(left), Some(right)) => self.tree.merge(left, right),
+            (Some(res), None) | (None, Some(res)) => res,
+            (None, None) => self.tree.neutral,
+        }
+    }
     
     pub fn init_with(&mut self, values: &[T]) {
-        self.tree.init_with(values);        
+        self.tree.init_padded(values);
         self.delayed.clear();
-        self.delayed.resize(values.len(), self.neutral);
+        self.delayed.resize(self.tree.len(), None);
+        self.len = values.len();
+    }
+
+    /// Pushes every pending value down to the leaves in O(n),
+    /// returns the current values
+    pub fn to_vec(&mut self) -> Vec<T> {
+        for vertex in Self::START_VERTEX..self.tree.len() {
+            self.push_children(vertex);
+        }
+
+        let first_leaf = self.tree.len();
+        self.tree.data[first_leaf..first_leaf + self.len].to_vec()
+    }
+
+    pub fn into_vec(mut self) -> Vec<T> {
+        self.to_vec()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
     }
 
     fn build(&mut self, vertex: usize) {
@@ -262,23 +676,32 @@ where
         while vertex > Self::START_VERTEX {
             vertex = parent(vertex);
             let (left, right) = children(vertex);
-            self.tree.data[vertex] = self.lazy(
-                self.tree.merge(self.tree.data[left], self.tree.data[right]),
-                self.delayed[vertex]
-            );
+            let merged = self.tree.merge(self.tree.data[left], self.tree.data[right]);
+            self.tree.data[vertex] = match self.delayed[vertex] {
+                Some(delayed) => self.lazy(merged, delayed),
+                None => merged,
+            };
         }
     }
 
     fn push(&mut self, vertex: usize) {
         for bit in (1..=self.height()).rev() {
-            let ancestor = vertex >> bit;
+            self.push_children(vertex >> bit);
+        }
+    }
 
-            if self.delayed[ancestor] != self.neutral {
-                let (left, right) = children(ancestor);
-                self.apply(left, self.delayed[ancestor]); 
-                self.apply(right, self.delayed[ancestor]); 
-                self.delayed[ancestor] = self.neutral;
-            }
+    fn apply_pending(&self, res: Option<T>, vertex: usize) -> Option<T> {
+        match (res, self.delayed.get(vertex)) {
+            (Some(res), Some(Some(delayed))) => Some(self.lazy(res, *delayed)),
+            _ => res,
+        }
+    }
+
+    fn push_children(&mut self, vertex: usize) {
+        if let Some(delayed) = self.delayed[vertex].take() {
+            let (left, right) = children(vertex);
+            self.apply(left, delayed); 
+            self.apply(right, delayed); 
         }
     }
 
@@ -286,7 +709,10 @@ where
         self.tree.data[vertex] = self.lazy(self.tree.data[vertex], value);
         
         if self.is_not_leaf(vertex) {
-            self.delayed[vertex] = self.lazy(self.delayed[vertex], value); 
+            self.delayed[vertex] = Some(match self.delayed[vertex] {
+                Some(delayed) => self.lazy(delayed, value),
+                None => value,
+            }); 
         }
     }
 
@@ -307,38 +733,164 @@ where
     }
 }
 
-impl<T, M, S> AssignmentSegmentTree<T, M, S> 
+impl<T, M> SegmentTree<T, M> 
+where
+    T: Copy + Mul<Output = T> + TryFrom<usize>,
+    M: Fn(T, T) -> T,
+{
+    /// with_assignment for sums, a segment of len elements is value * len
+    pub fn with_sum_assignment(self) -> AssignmentSegmentTree<T, M, fn(T, usize) -> T> {
+        self.with_assignment(scale)
+    }
+}
+
+impl<T, M, S, A> AssignmentSegmentTree<T, M, S, A> 
 where
     T: Copy,
     M: Fn(T, T) -> T,
     S: Fn(T, usize) -> T,
+    A: Fn(T, T, usize) -> T,
 {
     const START_VERTEX: usize = SegmentTree::<T, M>::START_VERTEX;
 
     pub fn new(tree: SegmentTree<T, M>, segment_fn: S) -> Self {
-        let delayed = vec![None; tree.len()];
-
-        Self {
+        let mut res = Self {
             tree,
-            delayed,
+            delayed: Vec::new(),
+            len: 0,
             segment_fn,
+            add_fn: None,
+        };
+
+        let values = res.tree.data[res.tree.len()..].to_vec();
+        res.init_with(&values);
+        res
+    }
+
+    /// add_fn(value, delta, len) adds delta to a segment of len elements,
+    /// pending adds are composed with len = 1
+    ///
+    /// We can not ensure this requirments with Rust
+    /// (1) add(add(a, b, k), c, k) = add(a, add(b, c, 1), k)
+    /// (2) add(merge(a, b), c, k + m) = merge(add(a, c, k), add(b, c, m))
+    /// (3) add(segment(a, k), b, k) = segment(add(a, b, 1), k)
+    pub fn with_add<B: Fn(T, T, usize) -> T>(self, add_fn: B) -> AssignmentSegmentTree<T, M, S, B> {
+        AssignmentSegmentTree {
+            tree: self.tree,
+            delayed: self.delayed,
+            len: self.len,
+            segment_fn: self.segment_fn,
+            add_fn: Some(add_fn),
         }
     }
    
     pub fn assign_single(&mut self, position: usize, value: T) {
+        check_position(position, self.len).unwrap_or_else(|err| panic!("{err}"));
         self.push(position, position + 1);
 
         let vertex = self.tree.vertex_from_position(position);
-        self.apply(vertex, value, 1);
+        self.apply(vertex, Tag::Assign(value), 1);
         self.build(position, position + 1);
     }
  
+    /// Wraps around when left > right
+    pub fn assign_circular(&mut self, left: usize, right: usize, value: T) {
+        if left <= right {
+            self.assign(left, right, value)
+        } else {
+            self.assign(left, self.len - 1, value);
+            self.assign(0, right, value);
+        }
+    }
+
     pub fn assign_range<R: RangeBounds<usize>>(&mut self, range: R, value: T) {
-        let (left, right) = self.tree.range_into_segment(range);
-        self.assign(left, right, value)
+        if let Some((left, right)) = range_into_segment(range, self.len) {
+            self.assign(left, right, value);
+        }
+    }
+
+    pub fn try_assign(&mut self, left: usize, right: usize, value: T) -> Result<(), RangeError> {
+        check_segment(left, right, self.len)?;
+        self.assign(left, right, value);
+        Ok(())
     }
 
     pub fn assign(&mut self, left: usize, right: usize, value: T) {
+        self.update(left, right, Tag::Assign(value))
+    }
+
+    /// Wraps around when left > right
+    pub fn modify_circular(&mut self, left: usize, right: usize, delta: T) {
+        if left <= right {
+            self.modify(left, right, delta)
+        } else {
+            self.modify(left, self.len - 1, delta);
+            self.modify(0, right, delta);
+        }
+    }
+
+    pub fn modify_range<R: RangeBounds<usize>>(&mut self, range: R, delta: T) {
+        if let Some((left, right)) = range_into_segment(range, self.len) {
+            self.modify(left, right, delta);
+        }
+    }
+
+    /// Panics if the tree was not built with_add
+    pub fn modify(&mut self, left: usize, right: usize, delta: T) {
+        assert!(self.add_fn.is_some(), "add function is not set, use with_add");
+        self.update(left, right, Tag::Add(delta))
+    }
+   
+    /// Wraps around when left > right, e.g. (5, 1) on length 8 is 5, 6, 7, 0, 1
+    pub fn query_circular(&mut self, left: usize, right: usize) -> T {
+        if left <= right {
+            self.query(left, right)
+        } else {
+            let suffix = self.query(left, self.len - 1);
+            let prefix = self.query(0, right);
+            self.tree.merge(suffix, prefix)
+        }
+    }
+
+    pub fn query_range<R: RangeBounds<usize>>(&mut self, range: R) -> T {
+        match range_into_segment(range, self.len) {
+            Some((left, right)) => self.query(left, right),
+            None => self.tree.neutral,
+        }
+    }
+
+    /// Gives neutral when left > right
+    pub fn query(&mut self, left: usize, right: usize) -> T {
+        check_position(right, self.len).unwrap_or_else(|err| panic!("{err}"));
+        if left > right {
+            return self.tree.neutral;
+        }
+
+        self.push(left, left + 1);
+        self.push(right, right + 1);
+   
+        self.tree.query(left, right)
+    }   
+    
+    pub fn init_with(&mut self, values: &[T]) {
+        self.tree.init_padded(values);
+        self.delayed.clear();
+        self.delayed.resize(self.tree.len(), Tag::None);
+        self.len = values.len();
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn update(&mut self, left: usize, right: usize, tag: Tag<T>) {
+        check_position(right, self.len).unwrap_or_else(|err| panic!("{err}"));
         self.push(left, left + 1);
         self.push(right, right + 1);
         
@@ -348,13 +900,13 @@ where
 
         while left_vertex < right_vertex {
             if (left_vertex & 1) == 1 {
-                self.apply(left_vertex, value, seg_len);
+                self.apply(left_vertex, tag, seg_len);
                 left_vertex += 1;
             }
             
             if (right_vertex & 1) == 1 {
                 right_vertex -= 1;
-                self.apply(right_vertex, value, seg_len);
+                self.apply(right_vertex, tag, seg_len);
             }
 
             left_vertex = parent(left_vertex);
@@ -365,24 +917,6 @@ where
         self.build(left, left + 1);
         self.build(right, right + 1);
     }
-   
-    pub fn query_range<R: RangeBounds<usize>>(&mut self, range: R) -> T {
-        let (left, right) = self.tree.range_into_segment(range);
-        self.query(left, right)
-    }
-
-    pub fn query(&mut self, left: usize, right: usize) -> T {
-        self.push(left, left + 1);
-        self.push(right, right + 1);
-   
-        self.tree.query(left, right)
-    }   
-    
-    pub fn init_with(&mut self, values: &[T]) {
-        self.tree.init_with(values);        
-        self.delayed.clear();
-        self.delayed.resize(values.len(), None);
-    }
 
     fn build(&mut self, left: usize, right: usize) {
         let mut seg_len = 2;
@@ -403,15 +937,18 @@ where
     }
 
     fn recalculate(&mut self, vertex: usize, seg_len: usize) {
-        match self.delayed[vertex] {
-            Some(delayed) => self.tree.data[vertex] = self.segment(delayed, seg_len),
-            None => {
-                let (left, right) = children(vertex);
-                self.tree.data[vertex] = self.tree.merge(self.tree.data[left], self.tree.data[right]);
-            } 
-        }
+        let (left, right) = children(vertex);
+        let merged = self.tree.merge(self.tree.data[left], self.tree.data[right]);
+
+        self.tree.data[vertex] = match self.delayed[vertex] {
+            Tag::Assign(value) => self.segment(value, seg_len),
+            Tag::Add(delta) => self.add(merged, delta, seg_len),
+            Tag::None => merged,
+        };
     }
 
+    /// Pushes tags on every path from the root down to the leaves of left..right,
+    /// seg_len halves exactly on every level since the leaves are padded
     fn push(&mut self, left: usize, right: usize) {
         let mut height = self.height();
         let mut seg_len = 1 << (height - 1);
@@ -421,11 +958,11 @@ where
 
         while height > 0 {
             for vertex in (left_vertex >> height)..=(right_vertex >> height) {
-                if let Some(delayed) = self.delayed[vertex] {
+                let tag = core::mem::replace(&mut self.delayed[vertex], Tag::None);
+                if !matches!(tag, Tag::None) {
                     let (left_child, right_child) = children(vertex);
-                    self.apply(left_child, delayed, seg_len); 
-                    self.apply(right_child, delayed, seg_len); 
-                    self.delayed[vertex] = None;
+                    self.apply(left_child, tag, seg_len); 
+                    self.apply(right_child, tag, seg_len); 
                 }
             }
 
@@ -434,11 +971,26 @@ where
         }
     }
 
-    fn apply(&mut self, vertex: usize, value: T, seg_len: usize) {
-        self.tree.data[vertex] = self.segment(value, seg_len);
+    /// Assign overwrites a pending add, add after assign folds into the assigned value
+    fn apply(&mut self, vertex: usize, tag: Tag<T>, seg_len: usize) {
+        let pending = if self.is_not_leaf(vertex) { self.delayed[vertex] } else { Tag::None };
+
+        let (value, tag) = match (tag, pending) {
+            (Tag::Assign(value), _) => (self.segment(value, seg_len), tag),
+            (Tag::Add(delta), Tag::Assign(value)) => {
+                (self.add(self.tree.data[vertex], delta, seg_len), Tag::Assign(self.add(value, delta, 1)))
+            }
+            (Tag::Add(delta), Tag::Add(pending)) => {
+                (self.add(self.tree.data[vertex], delta, seg_len), Tag::Add(self.add(pending, delta, 1)))
+            }
+            (Tag::Add(delta), Tag::None) => (self.add(self.tree.data[vertex], delta, seg_len), tag),
+            (Tag::None, _) => return,
+        };
+
+        self.tree.data[vertex] = value;
         
         if self.is_not_leaf(vertex) {
-            self.delayed[vertex] = Some(value); 
+            self.delayed[vertex] = tag; 
         }
     }
 
@@ -457,22 +1009,248 @@ where
     fn segment(&self, value: T, len: usize) -> T {
         (self.segment_fn)(value, len)
     }
-}
 
-#[inline]
-const fn parent(vertex: usize) -> usize {
-    vertex >> 1
+    #[inline]
+    fn add(&self, value: T, delta: T, len: usize) -> T {
+        let add_fn = self.add_fn.as_ref().expect("add function is not set, use with_add");
+        add_fn(value, delta, len)
+    }
 }
 
-#[inline]
-const fn children(vertex: usize) -> (usize, usize) {
-    let left = vertex << 1;
+impl<T, U, M, A, C> LazyUpdateSegmentTree<T, U, M, A, C> 
+where
+    T: Copy,
+    U: Copy,
+    M: Fn(T, T) -> T,
+    A: Fn(T, U, usize) -> T,
+    C: Fn(U, U) -> U,
+{
+    pub fn new(tree: SegmentTree<T, M>, apply_fn: A, compose_fn: C) -> Self {
+        let mut res = Self {
+            tree,
+            delayed: Vec::new(),
+            len: 0,
+            apply_fn,
+            compose_fn,
+        };
+
+        let values = res.tree.data[res.tree.len()..].to_vec();
+        res.init_with(&values);
+        res
+    }
+
+    pub fn init_with(&mut self, values: &[T]) {
+        self.tree.init_padded(values);
+        self.delayed.clear();
+        self.delayed.resize(self.tree.len(), None);
+        self.len = values.len();
+    }
+
+    /// Wraps around when left > right
+    pub fn modify_circular(&mut self, left: usize, right: usize, update: U) {
+        if left <= right {
+            self.modify(left, right, update)
+        } else {
+            self.modify(left, self.len - 1, update);
+            self.modify(0, right, update);
+        }
+    }
+
+    pub fn modify_range<R: RangeBounds<usize>>(&mut self, range: R, update: U) {
+        if let Some((left, right)) = range_into_segment(range, self.len) {
+            self.modify(left, right, update);
+        }
+    }
+
+    pub fn try_modify(&mut self, left: usize, right: usize, update: U) -> Result<(), RangeError> {
+        check_segment(left, right, self.len)?;
+        self.modify(left, right, update);
+        Ok(())
+    }
+
+    pub fn modify(&mut self, left: usize, right: usize, update: U) {
+        check_position(right, self.len).unwrap_or_else(|err| panic!("{err}"));
+        if left > right {
+            return;
+        }
+
+        let left_vertex = self.tree.vertex_from_position(left);
+        let right_vertex = self.tree.vertex_from_position(right + 1);
+        self.push_borders(left_vertex, right_vertex);
+
+        let (mut l, mut r) = (left_vertex, right_vertex);
+        while l < r {
+            if (l & 1) == 1 {
+                self.apply(l, update);
+                l += 1;
+            }
+            
+            if (r & 1) == 1 {
+                r -= 1;
+                self.apply(r, update);
+            }
+
+            l = parent(l);
+            r = parent(r);
+        }
+
+        for bit in 1..=self.height() {
+            if (left_vertex >> bit) << bit != left_vertex {
+                self.recalculate(left_vertex >> bit);
+            }
+
+            if (right_vertex >> bit) << bit != right_vertex {
+                self.recalculate((right_vertex - 1) >> bit);
+            }
+        }
+    }
+
+    /// Wraps around when left > right, e.g. (5, 1) on length 8 is 5, 6, 7, 0, 1
+    pub fn query_circular(&mut self, left: usize, right: usize) -> T {
+        if left <= right {
+            self.query(left, right)
+        } else {
+            let suffix = self.query(left, self.len - 1);
+            let prefix = self.query(0, right);
+            self.tree.merge(suffix, prefix)
+        }
+    }
+
+    pub fn query_range<R: RangeBounds<usize>>(&mut self, range: R) -> T {
+        match range_into_segment(range, self.len) {
+            Some((left, right)) => self.query(left, right),
+            None => self.tree.neutral,
+        }
+    }
+
+    pub fn query(&mut self, left: usize, right: usize) -> T {
+        check_position(right, self.len).unwrap_or_else(|err| panic!("{err}"));
+        if left > right {
+            return self.tree.neutral;
+        }
+
+        let left_vertex = self.tree.vertex_from_position(left);
+        let right_vertex = self.tree.vertex_from_position(right + 1);
+        self.push_borders(left_vertex, right_vertex);
+
+        self.tree.query(left, right)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Pushes ancestors of [left_vertex, right_vertex) which are not fully covered
+    fn push_borders(&mut self, left_vertex: usize, right_vertex: usize) {
+        for bit in (1..=self.height()).rev() {
+            if (left_vertex >> bit) << bit != left_vertex {
+                self.push(left_vertex >> bit);
+            }
+
+            if (right_vertex >> bit) << bit != right_vertex {
+                self.push((right_vertex - 1) >> bit);
+            }
+        }
+    }
+
+    fn push(&mut self, vertex: usize) {
+        if let Some(update) = self.delayed[vertex].take() {
+            let (left, right) = children(vertex);
+            self.apply(left, update);
+            self.apply(right, update);
+        }
+    }
+
+    fn apply(&mut self, vertex: usize, update: U) {
+        let seg_len = self.segment_len(vertex);
+        self.tree.data[vertex] = (self.apply_fn)(self.tree.data[vertex], update, seg_len);
+
+        if vertex < self.tree.len() {
+            self.delayed[vertex] = Some(match self.delayed[vertex] {
+                Some(delayed) => (self.compose_fn)(delayed, update),
+                None => update,
+            });
+        }
+    }
+
+    #[inline]
+    fn recalculate(&mut self, vertex: usize) {
+        let (left, right) = children(vertex);
+        self.tree.data[vertex] = self.tree.merge(self.tree.data[left], self.tree.data[right]);
+    }
+
+    /// Number of not padded leaves under vertex
+    fn segment_len(&self, vertex: usize) -> usize {
+        let level = self.height() - (usize::BITS - 1 - vertex.leading_zeros()) as usize;
+        let first = (vertex << level) - self.tree.len();
+        self.len.saturating_sub(first).min(1 << level)
+    }
+
+    /// Number of levels above the leaves
+    #[inline]
+    fn height(&self) -> usize {
+        self.tree.len().trailing_zeros() as usize
+    }
+}
+
+/// Compares only the leaves, merge functions can not be compared,
+/// so trees with different merges over the same values are equal
+impl<T, M> PartialEq for SegmentTree<T, M>
+where
+    T: Copy + PartialEq,
+    M: Fn(T, T) -> T,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.data[self.len()..] == other.data[other.len()..]
+    }
+}
+
+impl<T, M> Eq for SegmentTree<T, M>
+where
+    T: Copy + Eq,
+    M: Fn(T, T) -> T,
+{
+}
+
+/// Same as try_range_into_segment, but None for an empty range,
+/// panics if the range ends out of bounds
+fn range_into_segment<R: RangeBounds<usize>>(range: R, len: usize) -> Option<(usize, usize)> {
+    match try_range_into_segment(range, len) {
+        Ok(segment) => Some(segment),
+        Err(RangeError::Empty { .. }) => None,
+        Err(err) => panic!("{err}"),
+    }
+}
+
+fn scale<T: Mul<Output = T> + TryFrom<usize>>(value: T, len: usize) -> T {
+    match T::try_from(len) {
+        Ok(len) => value * len,
+        Err(_) => panic!("segment length {len} does not fit in the value type"),
+    }
+}
+
+#[inline]
+const fn parent(vertex: usize) -> usize {
+    vertex >> 1
+}
+
+#[inline]
+const fn children(vertex: usize) -> (usize, usize) {
+    let left = vertex << 1;
     (left, left | 1)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
     use std::ops::Add;
 
     #[test]
@@ -498,8 +1276,8 @@ mod tests {
 
         segtree.modify(0, 2, 2);
         
-        for i in 0..=2 {
-            values[i] += 2;
+        for value in &mut values[..=2] {
+            *value += 2;
         }
 
         for l in 0..values.len() {
@@ -509,6 +1287,54 @@ mod tests {
         }
     }
     
+    #[test]
+    fn lazy_len_segment_tree() {
+        let mut rng = SmallRng::seed_from_u64(1);
+
+        for len in 1..=64 {
+            let mut values: Vec<i64> = (0..len).map(|_| rng.gen_range(-100..100)).collect();
+            let mut segtree = SegmentTree::build(&values, <i64 as Add>::add, 0)
+                .with_lazy_len(|sum, delta, len| sum + delta * len as i64);
+
+            for _ in 0..100 {
+                let l = rng.gen_range(0..len);
+                let r = rng.gen_range(l..len);
+
+                if rng.gen_bool(0.5) {
+                    let delta = rng.gen_range(-100..100);
+                    segtree.modify_range(l..=r, delta);
+                    values[l..=r].iter_mut().for_each(|value| *value += delta);
+                } else {
+                    assert_eq!(segtree.query_range(l..=r), values[l..=r].iter().sum::<i64>(), "len: {len}, l: {l}, r: {r}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn lazy_segment_tree_without_eq() {
+        let mut values = [1.5, -3.0, 2.25, 5.0, 4.5];
+        let mut segtree = SegmentTree::build(&values, f64::max, f64::NEG_INFINITY)
+            .with_lazy(<f64 as Add>::add, 0.0);
+
+        segtree.modify(1, 3, 0.5);
+        segtree.modify(0, 1, -2.0);
+        
+        for value in &mut values[1..=3] {
+            *value += 0.5;
+        }
+        for value in &mut values[0..=1] {
+            *value -= 2.0;
+        }
+
+        for l in 0..values.len() {
+            for r in l..values.len() {
+                let expected = values[l..=r].iter().copied().fold(f64::NEG_INFINITY, f64::max);
+                assert_eq!(segtree.query(l, r), expected, "l: {l}, r: {r}");
+            }
+        }
+    }
+    
     #[test]
     fn assignment_segment_tree() {
         let mut values = [1, 3, 2, 5, 4];
@@ -517,8 +1343,8 @@ mod tests {
 
         segtree.assign(0, 2, 2);
         
-        for i in 0..=2 {
-            values[i] = 2;
+        for value in &mut values[..=2] {
+            *value = 2;
         }
 
         for l in 0..values.len() {
@@ -528,15 +1354,590 @@ mod tests {
         }
     }
 
+    /// Merge keeps the first nonzero value, so it is not commutative
+    #[test]
+    fn lazy_segment_tree_non_commutative() {
+        const MOD: u64 = 1_000_000_007;
+
+        let mut rng = SmallRng::seed_from_u64(2);
+
+        for len in [3, 5, 6, 7, 100] {
+            let mut values: Vec<u64> = (0..len).map(|_| rng.gen_range(0..3)).collect();
+            let mut segtree = SegmentTree::build(&values, |a, b| if a != 0 { a } else { b }, 0)
+                .with_lazy(|value, factor| value * factor % MOD, 1);
+
+            for _ in 0..200 {
+                let l = rng.gen_range(0..len);
+                let r = rng.gen_range(l..len);
+
+                if rng.gen_bool(0.5) {
+                    let factor = rng.gen_range(1..MOD);
+                    segtree.modify(l, r, factor);
+
+                    for value in &mut values[l..=r] {
+                        *value = *value * factor % MOD;
+                    }
+                } else {
+                    let expected = values[l..=r].iter().copied().find(|&value| value != 0).unwrap_or(0);
+                    assert_eq!(segtree.query(l, r), expected, "len: {len}, l: {l}, r: {r}");
+                }
+            }
+        }
+    }
+
+    /// Merge composes affine maps x -> a * x + b, so it is not commutative
+    #[test]
+    fn assignment_segment_tree_non_commutative() {
+        const MOD: u64 = 998_244_353;
+
+        fn compose((a1, b1): (u64, u64), (a2, b2): (u64, u64)) -> (u64, u64) {
+            (a2 * a1 % MOD, (a2 * b1 + b2) % MOD)
+        }
+
+        let mut rng = SmallRng::seed_from_u64(2);
+
+        for len in [3, 5, 6, 7, 100] {
+            let mut values: Vec<(u64, u64)> = (0..len).map(|_| (rng.gen_range(0..MOD), rng.gen_range(0..MOD))).collect();
+            let mut segtree = SegmentTree::build(&values, compose, (1, 0))
+                .with_assignment(|f, k| (0..k).fold((1, 0), |acc, _| compose(acc, f)));
+
+            for _ in 0..200 {
+                let l = rng.gen_range(0..len);
+                let r = rng.gen_range(l..len);
+
+                if rng.gen_bool(0.5) {
+                    let f = (rng.gen_range(0..MOD), rng.gen_range(0..MOD));
+                    segtree.assign(l, r, f);
+                    values[l..=r].fill(f);
+                } else {
+                    let expected = values[l..=r].iter().fold((1, 0), |acc, &f| compose(acc, f));
+                    assert_eq!(segtree.query(l, r), expected, "len: {len}, l: {l}, r: {r}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn assignment_segment_tree_with_add() {
+        let mut rng = SmallRng::seed_from_u64(3);
+
+        for len in [1, 2, 3, 5, 6, 7, 16, 100] {
+            let mut values: Vec<i64> = (0..len).map(|_| rng.gen_range(-100..100)).collect();
+            let mut segtree = SegmentTree::build(&values, <i64 as Add>::add, 0)
+                .with_assignment(|value, len| value * len as i64)
+                .with_add(|sum, delta, len| sum + delta * len as i64);
+
+            for _ in 0..300 {
+                let l = rng.gen_range(0..len);
+                let r = rng.gen_range(l..len);
+                let x = rng.gen_range(-100..100);
+
+                match rng.gen_range(0..3) {
+                    0 => {
+                        segtree.assign(l, r, x);
+                        values[l..=r].fill(x);
+                    }
+                    1 => {
+                        segtree.modify_range(l..=r, x);
+                        values[l..=r].iter_mut().for_each(|value| *value += x);
+                    }
+                    _ => assert_eq!(segtree.query(l, r), values[l..=r].iter().sum::<i64>(), "len: {len}, l: {l}, r: {r}"),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn assignment_segment_tree_overlapping_assigns() {
+        let mut rng = SmallRng::seed_from_u64(4);
+
+        for len in [5, 7] {
+            let mut values: Vec<i32> = (0..len).map(|_| rng.gen_range(0..1_000)).collect();
+            let mut max_tree = SegmentTree::build(&values, i32::max, i32::MIN)
+                .with_assignment(|value, _| value);
+            let mut sum_tree = SegmentTree::build(&values, <i32 as Add>::add, 0)
+                .with_assignment(|value, len| value * len as i32);
+
+            for _ in 0..2_000 {
+                let l = rng.gen_range(0..len);
+                let r = rng.gen_range(l..len);
+                let x = rng.gen_range(0..1_000);
+
+                if rng.gen_bool(0.5) {
+                    max_tree.assign_range(l..=r, x);
+                    sum_tree.assign_range(l..r + 1, x);
+                    values[l..=r].fill(x);
+                }
+
+                for l in 0..len {
+                    for r in l..len {
+                        let max = *values[l..=r].iter().max().unwrap();
+                        assert_eq!(max_tree.query_range(l..=r), max, "len: {len}, l: {l}, r: {r}");
+                        assert_eq!(sum_tree.query_range(l..=r), values[l..=r].iter().sum::<i32>(), "len: {len}, l: {l}, r: {r}");
+                    }
+                }
+            }
+        }
+    }
+
+    /// Range affine update x -> a * x + b with range sum modulo a prime
+    #[test]
+    fn lazy_update_segment_tree() {
+        const MOD: u64 = 998_244_353;
+
+        let mut rng = SmallRng::seed_from_u64(5);
+
+        for len in 1..=40 {
+            let mut values: Vec<u64> = (0..len).map(|_| rng.gen_range(0..MOD)).collect();
+            let mut segtree = SegmentTree::build(&values, |a, b| (a + b) % MOD, 0)
+                .with_update(
+                    |sum, (a, b): (u64, u64), len| (a * sum + b * len as u64) % MOD,
+                    |(a1, b1), (a2, b2)| (a2 * a1 % MOD, (a2 * b1 + b2) % MOD),
+                );
+
+            for _ in 0..50 {
+                let l = rng.gen_range(0..len);
+                let r = rng.gen_range(l..len);
+
+                if rng.gen_bool(0.5) {
+                    let (a, b) = (rng.gen_range(0..MOD), rng.gen_range(0..MOD));
+                    segtree.modify(l, r, (a, b));
+                    
+                    for value in &mut values[l..=r] {
+                        *value = (a * *value + b) % MOD;
+                    }
+                } else {
+                    let expected = values[l..=r].iter().fold(0, |acc, value| (acc + value) % MOD);
+                    assert_eq!(segtree.query(l, r), expected, "len: {len}, l: {l}, r: {r}");
+                }
+            }
+        }
+    }
+
+    /// Up to 8 chars, merge is concatenation
+    type Chars = ([u8; 8], usize);
+
+    fn concat(left: Chars, right: Chars) -> Chars {
+        let mut res = left;
+        res.0[left.1..left.1 + right.1].copy_from_slice(&right.0[..right.1]);
+        res.1 += right.1;
+        res
+    }
+
+    fn chars(s: &str) -> Chars {
+        s.bytes().map(|c| ([c, 0, 0, 0, 0, 0, 0, 0], 1)).fold(([0; 8], 0), concat)
+    }
+
+    #[test]
+    fn circular_query() {
+        let values: Vec<Chars> = "abcdefgh".bytes().map(|c| ([c, 0, 0, 0, 0, 0, 0, 0], 1)).collect();
+        let segtree = SegmentTree::build(&values, concat, ([0; 8], 0));
+
+        assert_eq!(segtree.query_circular(5, 1), chars("fghab"));
+        assert_eq!(segtree.query_circular(2, 4), chars("cde"));
+        assert_eq!(segtree.query_circular(7, 0), chars("ha"));
+        assert_eq!(segtree.query_circular(1, 0), chars("bcdefgha"));
+
+        let mut segtree = SegmentTree::build(&values[..6], concat, ([0; 8], 0))
+            .with_assignment(|value, len| (0..len).fold(([0; 8], 0), |acc, _| concat(acc, value)));
+
+        segtree.assign_circular(4, 0, chars("x"));
+        assert_eq!(segtree.query_circular(3, 2), chars("dxxxbc"));
+        assert_eq!(segtree.query_circular(5, 1), chars("xxb"));
+    }
+
+    #[test]
+    fn lazy_circular_query() {
+        let mut values = [0, 2, 0, 0, 3, 0, 0];
+        let mut segtree = SegmentTree::build(&values, |a, b| if a != 0 { a } else { b }, 0)
+            .with_lazy(|value, factor| value * factor, 1);
+
+        segtree.modify_circular(4, 1, 5);
+        for position in [4, 5, 6, 0, 1] {
+            values[position] *= 5;
+        }
+
+        for left in 0..values.len() {
+            for right in 0..values.len() {
+                let positions = (left..).take_while(|&position| position % values.len() != right).chain([right]);
+                let positions: Vec<usize> = positions.map(|position| position % values.len()).collect();
+                let expected = positions.iter().map(|&position| values[position]).find(|&value| value != 0).unwrap_or(0);
+                assert_eq!(segtree.query_circular(left, right), expected, "left: {left}, right: {right}");
+            }
+        }
+    }
+
+    #[test]
+    fn batch_assign() {
+        let mut rng = SmallRng::seed_from_u64(6);
+
+        for len in [1, 5, 6, 64, 100] {
+            let values: Vec<i64> = (0..len).map(|_| rng.gen_range(-100..100)).collect();
+            let mut batched = SegmentTree::build(&values, <i64 as Add>::add, 0);
+            let mut sequential = SegmentTree::build(&values, <i64 as Add>::add, 0);
+
+            for k in [1, 2, 3, len / 2, len, 2 * len] {
+                let updates: Vec<(usize, i64)> = (0..k)
+                    .map(|_| (rng.gen_range(0..len), rng.gen_range(-100..100)))
+                    .collect();
+
+                batched.batch_assign(&updates);
+                for &(position, value) in &updates {
+                    sequential.assign_single(position, value);
+                }
+
+                assert_eq!(batched.data, sequential.data, "len: {len}, k: {k}");
+            }
+        }
+
+        let mut segtree = SegmentTree::build(&[0; 8], <i32 as Add>::add, 0);
+        segtree.batch_assign(&[(5, 1), (2, 7), (5, 3)]);
+        assert_eq!(segtree.query(0, 7), 10);
+    }
+
+    #[test]
+    fn assign_many_recomputes_once() {
+        use core::cell::Cell;
+
+        let merges = Cell::new(0);
+        let values: Vec<i64> = (0..100).collect();
+        let mut segtree = SegmentTree::build(&values, |a, b| {
+            merges.set(merges.get() + 1);
+            a + b
+        }, 0);
+        let mut sequential = SegmentTree::build(&values, <i64 as Add>::add, 0);
+
+        // leaves 100 and 199 are at different depths, their ancestors are
+        // 50, 25, 12, 6, 3, 1 and 99, 49, 24, 12, 6, 3, 1
+        merges.set(0);
+        segtree.assign_many(&[(0, 1_000), (99, -1_000)]);
+        assert_eq!(merges.get(), 9);
+
+        sequential.assign_single(0, 1_000);
+        sequential.assign_single(99, -1_000);
+        assert_eq!(segtree.data, sequential.data);
+    }
+
+    #[test]
+    fn lazy_query_ref() {
+        let mut rng = SmallRng::seed_from_u64(7);
+
+        for len in [1, 3, 6, 8, 100] {
+            let mut values: Vec<i64> = (0..len).map(|_| rng.gen_range(-100..100)).collect();
+            let mut segtree = SegmentTree::build(&values, i64::max, i64::MIN)
+                .with_lazy(<i64 as Add>::add, 0);
+
+            for _ in 0..300 {
+                let l = rng.gen_range(0..len);
+                let r = rng.gen_range(l..len);
+
+                if rng.gen_bool(0.4) {
+                    let delta = rng.gen_range(-100..100);
+                    segtree.modify(l, r, delta);
+                    values[l..=r].iter_mut().for_each(|value| *value += delta);
+                } else {
+                    let (data, delayed) = (segtree.tree.data.clone(), segtree.delayed.clone());
+                    let expected = *values[l..=r].iter().max().unwrap();
+
+                    assert_eq!(segtree.query_ref(l, r), expected, "len: {len}, l: {l}, r: {r}");
+                    assert_eq!((&segtree.tree.data, &segtree.delayed), (&data, &delayed));
+                    assert_eq!(segtree.query(l, r), expected, "len: {len}, l: {l}, r: {r}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn lazy_batch_assign() {
+        let mut rng = SmallRng::seed_from_u64(6);
+
+        for len in [3, 6, 100] {
+            let mut values: Vec<i64> = (0..len).map(|_| rng.gen_range(-100..100)).collect();
+            let mut segtree = SegmentTree::build(&values, i64::max, i64::MIN)
+                .with_lazy(<i64 as Add>::add, 0);
+
+            for _ in 0..200 {
+                let l = rng.gen_range(0..len);
+                let r = rng.gen_range(l..len);
+
+                match rng.gen_range(0..3) {
+                    0 => {
+                        let delta = rng.gen_range(-100..100);
+                        segtree.modify(l, r, delta);
+                        values[l..=r].iter_mut().for_each(|value| *value += delta);
+                    }
+                    1 => {
+                        let k = if rng.gen_bool(0.5) { rng.gen_range(1..4) } else { len };
+                        let updates: Vec<(usize, i64)> = (0..k)
+                            .map(|_| (rng.gen_range(0..len), rng.gen_range(-100..100)))
+                            .collect();
+
+                        segtree.batch_assign(&updates);
+                        for &(position, value) in &updates {
+                            values[position] = value;
+                        }
+                    }
+                    _ => assert_eq!(segtree.query(l, r), *values[l..=r].iter().max().unwrap(), "len: {len}, l: {l}, r: {r}"),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn lazy_to_vec() {
+        let mut rng = SmallRng::seed_from_u64(8);
+
+        for len in [1, 5, 8, 37] {
+            let mut values: Vec<i64> = (0..len).map(|_| rng.gen_range(-100..100)).collect();
+            let mut segtree = SegmentTree::build(&values, i64::max, i64::MIN)
+                .with_lazy(<i64 as Add>::add, 0);
+
+            for _ in 0..100 {
+                let l = rng.gen_range(0..len);
+                let r = rng.gen_range(l..len);
+                let delta = rng.gen_range(-100..100);
+                segtree.modify(l, r, delta);
+                values[l..=r].iter_mut().for_each(|value| *value += delta);
+
+                if rng.gen_bool(0.2) {
+                    assert_eq!(segtree.to_vec(), values, "len: {len}");
+                    assert_eq!(segtree.query(0, len - 1), *values.iter().max().unwrap());
+                }
+            }
+
+            assert_eq!(segtree.into_vec(), values, "len: {len}");
+        }
+    }
+
+    #[test]
+    fn rollback() {
+        let mut rng = SmallRng::seed_from_u64(9);
+        let mut values: Vec<i64> = (0..37).map(|_| rng.gen_range(-100..100)).collect();
+        let mut segtree = SegmentTree::build(&values, <i64 as Add>::add, 0);
+        let mut checkpoints = Vec::new();
+
+        for _ in 0..2_000 {
+            match rng.gen_range(0..10) {
+                0 => checkpoints.push((segtree.checkpoint(), values.clone())),
+                1 if !checkpoints.is_empty() => {
+                    let depth = rng.gen_range(0..checkpoints.len());
+                    let (checkpoint, snapshot) = checkpoints.drain(depth..).next().unwrap();
+                    segtree.rollback(checkpoint);
+                    values = snapshot;
+                }
+                2..=5 => {
+                    let position = rng.gen_range(0..values.len());
+                    let delta = rng.gen_range(-100..100);
+                    segtree.update_single(position, |value| value + delta);
+                    values[position] += delta;
+                }
+                _ => {
+                    let position = rng.gen_range(0..values.len());
+                    let value = rng.gen_range(-100..100);
+                    segtree.assign_single(position, value);
+                    values[position] = value;
+                }
+            }
+
+            let l = rng.gen_range(0..values.len());
+            let r = rng.gen_range(l..values.len());
+            assert_eq!(segtree.query(l, r), values[l..=r].iter().sum::<i64>(), "l: {l}, r: {r}");
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn rollback_invalidates_later_checkpoints() {
+        let mut segtree = SegmentTree::build(&[1, 2, 3], <i32 as Add>::add, 0);
+        let outer = segtree.checkpoint();
+        let inner = segtree.checkpoint();
+        segtree.assign_single(0, 5);
+        segtree.rollback(outer);
+        segtree.rollback(inner);
+    }
+
+    #[test]
+    fn checked_methods() {
+        let mut segtree = SegmentTree::build(&[1, 3, 2, 5, 4], <i32 as Add>::add, 0);
+
+        assert_eq!(segtree.try_query(1, 3), Ok(10));
+        assert_eq!(segtree.try_query(3, 1), Err(RangeError::Empty { start: 3, end: 2, len: 5 }));
+        assert_eq!(segtree.try_query(1, 5), Err(RangeError::OutOfBounds { position: 5, len: 5 }));
+        assert_eq!(segtree.try_query(0, usize::MAX), Err(RangeError::OutOfBounds { position: usize::MAX, len: 5 }));
+
+        assert_eq!(segtree.try_query_range(..), Ok(15));
+        assert_eq!(segtree.try_query_range(2..2), Err(RangeError::Empty { start: 2, end: 2, len: 5 }));
+        assert_eq!(segtree.try_query_range(..=usize::MAX), Err(RangeError::OutOfBounds { position: usize::MAX, len: 5 }));
+        assert_eq!(segtree.try_query_range(4..9), Err(RangeError::OutOfBounds { position: 8, len: 5 }));
+
+        assert_eq!(segtree.try_assign_single(usize::MAX, 0), Err(RangeError::OutOfBounds { position: usize::MAX, len: 5 }));
+        assert_eq!(segtree.try_assign_single(4, 0), Ok(()));
+        assert_eq!(segtree.query_range(..), 11);
+
+        let mut lazy = SegmentTree::build(&[1, 3, 2], i32::max, i32::MIN).with_lazy(<i32 as Add>::add, 0);
+        assert_eq!(lazy.try_modify(2, 1, 1), Err(RangeError::Empty { start: 2, end: 2, len: 3 }));
+        assert_eq!(lazy.try_modify(0, 3, 1), Err(RangeError::OutOfBounds { position: 3, len: 3 }));
+        assert_eq!(lazy.try_modify(0, 2, 1), Ok(()));
+        assert_eq!(lazy.query(0, 2), 4);
+
+        let mut assignment = SegmentTree::build(&[1, 3, 2], i32::min, i32::MAX).with_assignment(|x, _k| x);
+        assert_eq!(assignment.try_assign(0, usize::MAX, 1), Err(RangeError::OutOfBounds { position: usize::MAX, len: 3 }));
+        assert_eq!(assignment.try_assign(1, 2, 0), Ok(()));
+        assert_eq!(assignment.query(0, 2), 0);
+
+        assert_eq!(RangeError::OutOfBounds { position: 5, len: 5 }.to_string(), "position 5 is out of bounds for length 5");
+    }
+
+    #[test]
+    #[should_panic(expected = "position 5 is out of bounds for length 3")]
+    fn lazy_query_out_of_bounds() {
+        let mut segtree = SegmentTree::build(&[1, 3, 2], i32::max, i32::MIN).with_lazy(<i32 as Add>::add, 0);
+        segtree.query(0, 5);
+    }
+
+    #[test]
+    #[should_panic(expected = "position 3 is out of bounds for length 3")]
+    fn update_single_out_of_bounds() {
+        let mut segtree = SegmentTree::build(&[1, 3, 2], <i32 as Add>::add, 0);
+        segtree.update_single(3, |value| value + 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "position 7 is out of bounds for length 3")]
+    fn batch_assign_out_of_bounds() {
+        let mut segtree = SegmentTree::build(&[1, 3, 2], <i32 as Add>::add, 0);
+        segtree.batch_assign(&[(0, 5), (7, 1)]);
+    }
+
     #[test]
     fn range_query() {
         let values = [1, 3, 2, 5, 4];
         let segtree = SegmentTree::build(&values, <i32 as Add>::add, 0);
 
-        assert_eq!(segtree.query_range(..), values[..].iter().fold(0, <i32 as Add<&i32>>::add));
-        assert_eq!(segtree.query_range(1..), values[1..].iter().fold(0, <i32 as Add<&i32>>::add));
-        assert_eq!(segtree.query_range(..=3), values[..=3].iter().fold(0, <i32 as Add<&i32>>::add));
-        assert_eq!(segtree.query_range(2..4), values[2..4].iter().fold(0, <i32 as Add<&i32>>::add));
+        assert_eq!(segtree.query_range(..), values[..].iter().sum::<i32>());
+        assert_eq!(segtree.query_range(1..), values[1..].iter().sum::<i32>());
+        assert_eq!(segtree.query_range(..=3), values[..=3].iter().sum::<i32>());
+        assert_eq!(segtree.query_range(2..4), values[2..4].iter().sum::<i32>());
+    }
+
+    #[test]
+    fn empty_range_query() {
+        let values = [1, 3, 2, 5, 4];
+        let segtree = SegmentTree::build(&values, <i32 as Add>::add, 0);
+
+        assert_eq!(segtree.query_range(3..3), 0);
+        assert_eq!(segtree.query_range(0..0), 0);
+        assert_eq!(segtree.query_range(5..5), 0);
+        assert_eq!(segtree.query(4, 3), 0);
+        assert_eq!(segtree.query(3, 0), 0);
+
+        let mut lazy = SegmentTree::build(&values, i32::max, i32::MIN)
+            .with_lazy(<i32 as Add>::add, 0);
+        lazy.modify(0, 4, 1);
+        assert_eq!(lazy.query(5, 4), i32::MIN);
+        assert_eq!(lazy.query(0, 4), 6);
+
+        let mut assignment = SegmentTree::build(&values, <i32 as Add>::add, 0)
+            .with_assignment(|value, len| value * len as i32);
+        assignment.assign(1, 3, 2);
+        assert_eq!(assignment.query(2, 1), 0);
+        assert_eq!(assignment.query(0, 4), 11);
+    }
+
+    #[test]
+    fn sum_assignment() {
+        let mut segtree = SegmentTree::build(&[1, 2, 3, 4, 5], <i32 as Add>::add, 0)
+            .with_sum_assignment();
+        segtree.assign_range(0..=2, 3);
+        assert_eq!(segtree.query(0, 2), 9);
+        assert_eq!(segtree.query_range(..), 18);
+
+        let mut wide = SegmentTree::build(&[0u64; 100], <u64 as Add>::add, 0)
+            .with_sum_assignment();
+        wide.assign_range(.., 7);
+        wide.assign(10, 19, 1);
+        assert_eq!(wide.query(0, 99), 640);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn assign_alias() {
+        let values = [3, 1, 4, 1, 5];
+        let mut canonical = SegmentTree::build(&values, <i32 as Add>::add, 0);
+        let mut alias = SegmentTree::build(&values, <i32 as Add>::add, 0);
+
+        for (position, value) in [(0, 2), (4, -1), (2, 7)] {
+            canonical.assign_single(position, value);
+            alias.assign(position, value);
+            assert!(canonical == alias);
+            assert_eq!(canonical.query(0, 4), alias.query(0, 4));
+        }
+    }
+
+    #[test]
+    fn prefix_folds() {
+        let segtree = SegmentTree::build(&[4, -1, 7, 0, 3, 2], <i32 as Add>::add, 0);
+        let folds: Vec<i32> = segtree.prefix_folds().collect();
+        assert_eq!(folds.len(), segtree.len());
+
+        for (i, &fold) in folds.iter().enumerate() {
+            assert_eq!(fold, segtree.query_range(..=i), "i: {i}");
+        }
+
+        let empty = SegmentTree::build(&[], <i32 as Add>::add, 0);
+        assert_eq!(empty.prefix_folds().count(), 0);
+    }
+
+    #[test]
+    fn neutral_and_merge() {
+        let values = [3, 8, 1, 9, 4];
+        let segtree = SegmentTree::build(&values, |a: i32, b: i32| a.max(b), i32::MIN);
+
+        let folded = values.iter().fold(segtree.neutral(), |res, &value| segtree.merge_pub(res, value));
+        assert_eq!(folded, segtree.query_range(..));
+        assert_eq!(segtree.neutral(), i32::MIN);
+        assert_eq!(segtree.merge_pub(segtree.query(0, 1), segtree.query(2, 4)), 9);
+    }
+
+    #[test]
+    fn compare_leaves() {
+        let values = [5, 1, 4, 2, 3];
+        let mut first = SegmentTree::build(&values, <i32 as Add>::add, 0);
+        let second = SegmentTree::build(&values, <i32 as Add>::add, 0);
+        assert!(first == second);
+
+        first.assign_single(2, 7);
+        assert!(first != second);
+        first.assign_single(2, 4);
+        assert!(first == second);
+        assert!(first != SegmentTree::build(&values[..4], <i32 as Add>::add, 0));
+    }
+
+    #[test]
+    fn empty_range_lazy_trees() {
+        let mut lazy = SegmentTree::build(&[], i32::max, i32::MIN)
+            .with_lazy(<i32 as Add>::add, 0);
+        lazy.modify_range(.., 1);
+        assert_eq!(lazy.query_range(..), i32::MIN);
+        assert_eq!(lazy.query_range(0..0), i32::MIN);
+
+        let mut assignment = SegmentTree::build(&[1, 2, 3], <i32 as Add>::add, 0)
+            .with_sum_assignment();
+        assignment.assign_range(1..1, 5);
+        assert_eq!(assignment.query_range(0..0), 0);
+        assert_eq!(assignment.query_range(..), 6);
+
+        let mut update = SegmentTree::build(&[], <i64 as Add>::add, 0)
+            .with_lazy_len(|value, delta, len| value + delta * len as i64);
+        update.modify_range(0..0, 3);
+        assert_eq!(update.query_range(..), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn empty_range_query_out_of_bounds() {
+        let segtree = SegmentTree::build(&[1, 2, 3], <i32 as Add>::add, 0);
+        segtree.query_range(4..4);
     }
 }
 
@@ -0,0 +1,68 @@
+use rayon::prelude::*;
+
+use super::SegmentTree;
+
+impl<T, M> SegmentTree<T, M>
+where
+    T: Copy + Send + Sync,
+    M: Fn(T, T) -> T + Sync,
+{
+    /// Same tree as build, every level of vertices is merged in parallel,
+    /// as a level only depends on the one below it
+    pub fn build_parallel(values: &[T], merge: M, neutral: T) -> Self {
+        let mut res = Self::new(merge, neutral);
+        let len = values.len();
+
+        res.data.resize(len, neutral);
+        res.data.extend_from_slice(values);
+
+        let merge = &res.merge_fn;
+        // the deepest level holds the vertex len - 1
+        let mut level_start = match len {
+            0 | 1 => 0,
+            _ => 1 << (len - 1).ilog2(),
+        };
+
+        while level_start >= Self::START_VERTEX {
+            let level_end = (level_start << 1).min(len);
+            let (upper, lower) = res.data.split_at_mut(level_start << 1);
+
+            upper[level_start..level_end]
+                .par_iter_mut()
+                .enumerate()
+                .for_each(|(offset, value)| {
+                    let left = 2 * offset;
+                    *value = merge(lower[left], lower[left + 1]);
+                });
+
+            level_start >>= 1;
+        }
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::ops::Add;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn build_parallel() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let values: Vec<u64> = (0..1_000_000).map(|_| rng.gen_range(0..1_000)).collect();
+
+        let parallel = SegmentTree::build_parallel(&values, <u64 as Add>::add, 0);
+        let sequential = SegmentTree::build(&values, <u64 as Add>::add, 0);
+        assert_eq!(parallel.data, sequential.data);
+        assert_eq!(parallel.query_range(..), values.iter().sum::<u64>());
+
+        for len in [0, 1, 2, 3, 5, 8, 13] {
+            let parallel = SegmentTree::build_parallel(&values[..len], u64::max, 0);
+            let sequential = SegmentTree::build(&values[..len], u64::max, 0);
+            assert_eq!(parallel.data, sequential.data, "len: {len}");
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use alloc::vec::Vec;
+
+use core::ops::RangeBounds;
+
+/// Every assignment creates a new version sharing unchanged nodes
+/// with the old one, so memory is O(n + q log n) for q assignments
+///
+/// We can not ensure this requirments with Rust
+/// (1) merge(a, neutral) = a
+/// (2) merge(merge(a, b), c) = merge(a, merge(b, c))
+pub struct PersistentSegmentTree<T, M>
+where
+    T: Copy,
+    M: Fn(T, T) -> T,
+{
+    nodes: Vec<Node<T>>,
+    roots: Vec<usize>,
+    len: usize,
+    merge_fn: M,
+    neutral: T,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct VersionId(usize);
+
+#[derive(Clone, Copy, Debug)]
+struct Node<T> {
+    value: T,
+    left: usize,
+    right: usize,
+}
+
+impl<T, M> PersistentSegmentTree<T, M>
+where
+    T: Copy,
+    M: Fn(T, T) -> T,
+{
+    pub fn build(values: &[T], merge_fn: M, neutral: T) -> Self {
+        let mut res = Self {
+            nodes: Vec::with_capacity(2 * values.len()),
+            roots: Vec::new(),
+            len: values.len(),
+            merge_fn,
+            neutral,
+        };
+
+        let root = match values.len() {
+            0 => res.push_node(neutral, 0, 0),
+            len => res.build_node(values, 0, len),
+        };
+
+        res.roots.push(root);
+        res
+    }
+
+    /// Returns the version in which the tree was built
+    #[inline]
+    pub fn initial(&self) -> VersionId {
+        VersionId(0)
+    }
+
+    #[inline]
+    pub fn latest(&self) -> VersionId {
+        VersionId(self.roots.len() - 1)
+    }
+
+    #[inline]
+    pub fn versions(&self) -> usize {
+        self.roots.len()
+    }
+
+    /// Returns the new version, `version` itself stays unchanged
+    pub fn assign(&mut self, version: VersionId, position: usize, value: T) -> VersionId {
+        assert!(position < self.len, "position {position} is out of range for length {}", self.len);
+
+        let root = self.roots[version.0];
+        let root = self.assign_node(root, 0, self.len, position, value);
+        self.roots.push(root);
+        self.latest()
+    }
+
+    pub fn query_range<R: RangeBounds<usize>>(&self, version: VersionId, range: R) -> T {
+        match super::range_into_segment(range, self.len) {
+            Some((left, right)) => self.query(version, left, right),
+            None => self.neutral,
+        }
+    }
+
+    pub fn query(&self, version: VersionId, left: usize, right: usize) -> T {
+        if left > right || self.len == 0 {
+            return self.neutral;
+        }
+
+        self.query_node(self.roots[version.0], 0, self.len, left, right + 1)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn build_node(&mut self, values: &[T], left: usize, right: usize) -> usize {
+        if right - left == 1 {
+            return self.push_node(values[left], 0, 0);
+        }
+
+        let middle = (left + right) / 2;
+        let left_child = self.build_node(values, left, middle);
+        let right_child = self.build_node(values, middle, right);
+        self.push_merged(left_child, right_child)
+    }
+
+    fn assign_node(&mut self, node: usize, left: usize, right: usize, position: usize, value: T) -> usize {
+        if right - left == 1 {
+            return self.push_node(value, 0, 0);
+        }
+
+        let middle = (left + right) / 2;
+        let Node { left: mut left_child, right: mut right_child, .. } = self.nodes[node];
+
+        if position < middle {
+            left_child = self.assign_node(left_child, left, middle, position, value);
+        } else {
+            right_child = self.assign_node(right_child, middle, right, position, value);
+        }
+
+        self.push_merged(left_child, right_child)
+    }
+
+    /// Segment of the node is [left, right), query is [from, to)
+    fn query_node(&self, node: usize, left: usize, right: usize, from: usize, to: usize) -> T {
+        if to <= left || right <= from {
+            return self.neutral;
+        }
+
+        let node = &self.nodes[node];
+
+        if from <= left && right <= to {
+            return node.value;
+        }
+
+        let middle = (left + right) / 2;
+        self.merge(
+            self.query_node(node.left, left, middle, from, to),
+            self.query_node(node.right, middle, right, from, to),
+        )
+    }
+
+    fn push_merged(&mut self, left: usize, right: usize) -> usize {
+        let value = self.merge(self.nodes[left].value, self.nodes[right].value);
+        self.push_node(value, left, right)
+    }
+
+    fn push_node(&mut self, value: T, left: usize, right: usize) -> usize {
+        self.nodes.push(Node { value, left, right });
+        self.nodes.len() - 1
+    }
+
+    #[inline]
+    fn merge(&self, left: T, right: T) -> T {
+        (self.merge_fn)(left, right)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ops::Add;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn old_versions_stay_unchanged() {
+        let mut values = vec![1, 3, 2, 5, 4];
+        let mut tree = PersistentSegmentTree::build(&values, <i32 as Add>::add, 0);
+        let mut history = vec![(tree.initial(), values.clone())];
+
+        for (position, value) in [(2, 7), (0, -1), (4, 10), (2, 0)] {
+            let version = tree.assign(tree.latest(), position, value);
+            values[position] = value;
+            history.push((version, values.clone()));
+        }
+
+        let branch = tree.assign(history[1].0, 3, 100);
+        let mut branch_values = history[1].1.clone();
+        branch_values[3] = 100;
+        history.push((branch, branch_values));
+
+        for (version, values) in history {
+            for l in 0..values.len() {
+                for r in l..values.len() {
+                    assert_eq!(tree.query(version, l, r), values[l..=r].iter().sum::<i32>(), "l: {l}, r: {r}");
+                }
+            }
+        }
+
+        assert_eq!(tree.query_range(tree.latest(), 3..3), 0);
+        let empty = PersistentSegmentTree::build(&[], <i32 as Add>::add, 0);
+        assert_eq!(empty.query_range(empty.initial(), ..), 0);
+    }
+
+    /// Version i counts occurrences of values among the first i elements,
+    /// so the difference of two versions describes any subarray
+    #[test]
+    fn kth_smallest_in_range() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let values: Vec<i64> = (0..60).map(|_| rng.gen_range(-1_000_000..1_000_000)).collect();
+
+        let mut sorted = values.clone();
+        sorted.sort();
+        sorted.dedup();
+
+        let mut tree = PersistentSegmentTree::build(&vec![0; sorted.len()], <u32 as Add>::add, 0);
+        let mut prefix = vec![tree.initial()];
+        let mut counts = vec![0; sorted.len()];
+
+        for value in &values {
+            let position = sorted.binary_search(value).unwrap();
+            counts[position] += 1;
+            prefix.push(tree.assign(tree.latest(), position, counts[position]));
+        }
+
+        let kth = |l: usize, r: usize, k: u32| {
+            let count = |position| tree.query(prefix[r + 1], 0, position) - tree.query(prefix[l], 0, position);
+            let (mut lo, mut hi) = (0, sorted.len() - 1);
+
+            while lo < hi {
+                let middle = (lo + hi) / 2;
+                if count(middle) >= k {
+                    hi = middle;
+                } else {
+                    lo = middle + 1;
+                }
+            }
+
+            sorted[lo]
+        };
+
+        for l in 0..values.len() {
+            for r in l..values.len() {
+                let mut expected = values[l..=r].to_vec();
+                expected.sort();
+
+                for k in [0, (r - l) / 2, r - l] {
+                    assert_eq!(kth(l, r, k as u32 + 1), expected[k], "l: {l}, r: {r}, k: {k}");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use alloc::vec::Vec;
+
+/// Returned by SegmentTree::checkpoint, consumed by rollback
+#[derive(Debug, PartialEq, Eq)]
+pub struct Checkpoint {
+    depth: usize,
+    id: usize,
+}
+
+/// Overwritten vertex values, recorded while a checkpoint is active
+#[derive(Clone, Debug)]
+pub(super) struct UndoLog<T> {
+    entries: Vec<(usize, T)>,
+    /// Id and log length of every active checkpoint, outermost first
+    checkpoints: Vec<(usize, usize)>,
+    created: usize,
+}
+
+impl<T> UndoLog<T> {
+    #[inline]
+    pub(super) fn is_active(&self) -> bool {
+        !self.checkpoints.is_empty()
+    }
+
+    #[inline]
+    pub(super) fn record(&mut self, vertex: usize, old: T) {
+        if self.is_active() {
+            self.entries.push((vertex, old));
+        }
+    }
+
+    pub(super) fn checkpoint(&mut self) -> Checkpoint {
+        let checkpoint = Checkpoint {
+            depth: self.checkpoints.len(),
+            id: self.created,
+        };
+
+        self.created += 1;
+        self.checkpoints.push((checkpoint.id, self.entries.len()));
+        checkpoint
+    }
+
+    /// Returns entries to restore, latest first,
+    /// checkpoints made after checkpoint become invalid
+    pub(super) fn rollback(&mut self, checkpoint: Checkpoint) -> impl Iterator<Item = (usize, T)> + '_ {
+        let active = self.checkpoints.get(checkpoint.depth)
+            .is_some_and(|&(id, _)| id == checkpoint.id);
+        assert!(active, "checkpoint was already rolled back");
+
+        let (_, log_len) = self.checkpoints[checkpoint.depth];
+        self.checkpoints.truncate(checkpoint.depth);
+        self.entries.drain(log_len..).rev()
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.entries.clear();
+        self.checkpoints.clear();
+    }
+}
+
+impl<T> Default for UndoLog<T> {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+            checkpoints: Vec::new(),
+            created: 0,
+        }
+    }
+}
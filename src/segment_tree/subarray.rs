@@ -0,0 +1,121 @@
+use alloc::vec::Vec;
+
+use core::ops::{Add, RangeBounds};
+
+use super::SegmentTree;
+
+/// Sums of a segment for the maximum subarray sum, every sum is of
+/// a non empty subarray, so for negative values best is the largest one
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MaxSubarray<T> {
+    pub total: T,
+    /// Best sum of a prefix
+    pub prefix: T,
+    /// Best sum of a suffix
+    pub suffix: T,
+    /// Best sum of a subarray
+    pub best: T,
+}
+
+/// None for no elements
+pub type MaxSubarrayNode<T> = Option<MaxSubarray<T>>;
+pub type MaxSubarrayFn<T> = fn(MaxSubarrayNode<T>, MaxSubarrayNode<T>) -> MaxSubarrayNode<T>;
+
+impl<T: Copy> MaxSubarray<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            total: value,
+            prefix: value,
+            suffix: value,
+            best: value,
+        }
+    }
+}
+
+impl<T> SegmentTree<MaxSubarrayNode<T>, MaxSubarrayFn<T>>
+where
+    T: Copy + Ord + Add<Output = T>,
+{
+    /// Maximum subarray sum with point updates, assign Some(MaxSubarray::new(value))
+    pub fn max_subarray(values: &[T]) -> Self {
+        let values: Vec<MaxSubarrayNode<T>> = values.iter()
+            .map(|&value| Some(MaxSubarray::new(value)))
+            .collect();
+
+        SegmentTree::build(&values, merge, None)
+    }
+
+    pub fn best_range<R: RangeBounds<usize>>(&self, range: R) -> T {
+        let (left, right) = self.range_into_segment(range);
+        self.best(left, right)
+    }
+
+    /// Best sum of a non empty subarray of left..=right
+    pub fn best(&self, left: usize, right: usize) -> T {
+        assert!(left <= right, "range {left}..={right} is empty");
+        self.query(left, right).unwrap().best
+    }
+}
+
+fn merge<T>(left: MaxSubarrayNode<T>, right: MaxSubarrayNode<T>) -> MaxSubarrayNode<T>
+where
+    T: Copy + Ord + Add<Output = T>,
+{
+    match (left, right) {
+        (Some(left), Some(right)) => Some(MaxSubarray {
+            total: left.total + right.total,
+            prefix: left.prefix.max(left.total + right.prefix),
+            suffix: right.suffix.max(left.suffix + right.total),
+            best: left.best.max(right.best).max(left.suffix + right.prefix),
+        }),
+        (None, _) => right,
+        (_, None) => left,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    fn brute_force(values: &[i64]) -> i64 {
+        (0..values.len())
+            .flat_map(|l| (l..values.len()).map(move |r| values[l..=r].iter().sum()))
+            .max()
+            .unwrap()
+    }
+
+    #[test]
+    fn max_subarray() {
+        let mut rng = SmallRng::seed_from_u64(1);
+
+        for (len, low) in [(1, -10), (9, -10), (30, -10), (17, -100)] {
+            let mut values: Vec<i64> = (0..len).map(|_| rng.gen_range(low..10)).collect();
+            let mut segtree = SegmentTree::max_subarray(&values);
+
+            for _ in 0..300 {
+                if rng.gen_bool(0.3) {
+                    let position = rng.gen_range(0..len);
+                    let value = rng.gen_range(low..10);
+                    segtree.assign_single(position, Some(MaxSubarray::new(value)));
+                    values[position] = value;
+                }
+
+                let l = rng.gen_range(0..len);
+                let r = rng.gen_range(l..len);
+                assert_eq!(segtree.best(l, r), brute_force(&values[l..=r]), "l: {l}, r: {r}");
+            }
+        }
+
+        let negative = SegmentTree::max_subarray(&[-5, -2, -7]);
+        assert_eq!(negative.best_range(..), -2);
+        assert_eq!(negative.best_range(2..), -7);
+    }
+
+    #[test]
+    #[should_panic(expected = "range 2..2 is empty, length is 3")]
+    fn best_range_empty() {
+        SegmentTree::max_subarray(&[1, -2, 3]).best_range(2..2);
+    }
+}
@@ -1,67 +1,832 @@
-use std::ops::Add;
+use alloc::vec::Vec;
 
-pub struct SqrtDecomposition<T> {
+use core::ops::{Add, Bound, RangeBounds};
+
+/// Folds are kept per block, range adds to whole blocks are kept as block tags
+///
+/// We can not ensure this requirments with Rust
+/// (1) merge(a, neutral) = a
+/// (2) merge(merge(a, b), c) = merge(a, merge(b, c))
+pub struct SqrtDecomposition<T, M = fn(T, T) -> T, A = fn(T, T, usize) -> T>
+where
+    T: Copy,
+    M: Fn(T, T) -> T,
+    A: Fn(T, T, usize) -> T,
+{
     data: Vec<T>,
     blocks: Vec<T>,
+    /// Index of the first element of every block, blocks grow and shrink
+    /// with insert and remove
+    starts: Vec<usize>,
+    /// Added to every element of the block, but not to data yet
+    delayed: Vec<Option<T>>,
+    /// Blocks are rebuilt when one gets twice as long or empty
     block_len: usize,
+    merge_fn: M,
+    neutral: T,
+    add_fn: Option<A>,
 }
 
 impl<T> SqrtDecomposition<T>
 where
     T: Copy + Default + Add<Output = T>
 {
+    /// Sums with range add, scaling a delta by a count uses only Add,
+    /// by doubling in O(log count)
     pub fn new() -> Self {
         Self {
             data: Vec::new(),
             blocks: Vec::new(),
+            starts: Vec::new(),
+            delayed: Vec::new(),
             block_len: 0,
+            merge_fn: <T as Add>::add,
+            neutral: T::default(),
+            add_fn: Some(|sum, delta, len| sum + scale(delta, len)),
         }
     }
-    
+
     pub fn build(values: &[T]) -> Self {
         let mut res = Self::new();
         res.init_with(values);
         res
     }
 
+    /// Larger blocks favor updates, smaller blocks favor queries,
+    /// block_len is clamped to 1..=values.len()
+    pub fn with_block_len(values: &[T], block_len: usize) -> Self {
+        let mut res = Self::new();
+        res.init_blocks(values, block_len.clamp(1, values.len().max(1)));
+        res
+    }
+
+    /// Same as query
+    #[inline]
+    pub fn sum(&self, left: usize, right: usize) -> T {
+        self.query(left, right)
+    }
+}
+
+impl<T, M> SqrtDecomposition<T, M>
+where
+    T: Copy,
+    M: Fn(T, T) -> T,
+{
+    /// Range add is available after with_add
+    pub fn build_with(values: &[T], merge_fn: M, neutral: T) -> Self {
+        let mut res = Self {
+            data: Vec::new(),
+            blocks: Vec::new(),
+            starts: Vec::new(),
+            delayed: Vec::new(),
+            block_len: 0,
+            merge_fn,
+            neutral,
+            add_fn: None,
+        };
+
+        res.init_with(values);
+        res
+    }
+}
+
+impl<T, M, A> SqrtDecomposition<T, M, A>
+where
+    T: Copy,
+    M: Fn(T, T) -> T,
+    A: Fn(T, T, usize) -> T,
+{
+    /// add_fn(value, delta, len) adds delta to each of len values folded into value,
+    /// pending adds are composed with len = 1
+    ///
+    /// We can not ensure this requirments with Rust
+    /// (1) add(add(a, b, k), c, k) = add(a, add(b, c, 1), k)
+    /// (2) add(merge(a, b), c, k + m) = merge(add(a, c, k), add(b, c, m))
+    pub fn with_add<B: Fn(T, T, usize) -> T>(self, add_fn: B) -> SqrtDecomposition<T, M, B> {
+        SqrtDecomposition {
+            data: self.data,
+            blocks: self.blocks,
+            starts: self.starts,
+            delayed: self.delayed,
+            block_len: self.block_len,
+            merge_fn: self.merge_fn,
+            neutral: self.neutral,
+            add_fn: Some(add_fn),
+        }
+    }
+
     pub fn init_with(&mut self, values: &[T]) {
+        self.init_blocks(values, 1 + values.len().isqrt());
+    }
+
+    /// Recomputes the fold of the block containing index
+    pub fn assign(&mut self, index: usize, value: T) {
+        assert!(index < self.data.len(), "index {index} is out of range for length {}", self.data.len());
+
+        let block = self.block_of(index);
+        self.push(block);
+        self.data[index] = value;
+        self.recalculate(block);
+    }
+
+    /// Shifts the elements after index, amortized O(sqrt n),
+    /// rebuilds use blocks of 1 + sqrt(len) elements
+    pub fn insert(&mut self, index: usize, value: T) {
+        assert!(index <= self.data.len(), "index {index} is out of range for length {}", self.data.len());
+
+        if self.blocks.is_empty() {
+            self.data.insert(index, value);
+            self.rebuild();
+            return;
+        }
+
+        let block = self.block_of(index);
+        self.push(block);
+        self.data.insert(index, value);
+
+        for start in &mut self.starts[block + 1..] {
+            *start += 1;
+        }
+
+        let (start, end) = self.block_bounds(block);
+        if end - start > 2 * self.block_len {
+            self.rebuild();
+        } else {
+            self.recalculate(block);
+        }
+    }
+
+    /// Shifts the elements after index, amortized O(sqrt n),
+    /// rebuilds use blocks of 1 + sqrt(len) elements
+    pub fn remove(&mut self, index: usize) -> T {
+        assert!(index < self.data.len(), "index {index} is out of range for length {}", self.data.len());
+
+        let block = self.block_of(index);
+        self.push(block);
+        let value = self.data.remove(index);
+
+        for start in &mut self.starts[block + 1..] {
+            *start -= 1;
+        }
+
+        let (start, end) = self.block_bounds(block);
+        if start == end {
+            self.rebuild();
+        } else {
+            self.recalculate(block);
+        }
+
+        value
+    }
+
+    /// left inclusive
+    /// right inclusive
+    /// Panics if there is no add function
+    pub fn add_range(&mut self, left: usize, right: usize, delta: T) {
+        assert!(left <= right, "range {left}..={right} is empty");
+        assert!(right < self.data.len(), "index {right} is out of range for length {}", self.data.len());
+
+        let left_block = self.block_of(left);
+        let right_block = self.block_of(right);
+
+        if left_block == right_block {
+            self.add_partial(left, right, delta);
+        } else {
+            self.add_partial(left, self.block_bounds(left_block).1 - 1, delta);
+            self.add_partial(self.starts[right_block], right, delta);
+
+            for block in left_block + 1..right_block {
+                let (start, end) = self.block_bounds(block);
+                self.blocks[block] = self.add(self.blocks[block], delta, end - start);
+                self.delayed[block] = Some(match self.delayed[block] {
+                    Some(delayed) => self.add(delayed, delta, 1),
+                    None => delta,
+                });
+            }
+        }
+    }
+
+    /// Empty ranges give neutral
+    pub fn query_range<R: RangeBounds<usize>>(&self, range: R) -> T {
+        let start = match range.start_bound() {
+            Bound::Included(start) => *start,
+            Bound::Excluded(before_start) => *before_start + 1,
+            Bound::Unbounded => 0,
+        };
+
+        let end = match range.end_bound() {
+            Bound::Included(end) => *end + 1,
+            Bound::Excluded(after_end) => *after_end,
+            Bound::Unbounded => self.data.len(),
+        };
+
+        if start >= end {
+            self.neutral
+        } else {
+            self.query(start, end - 1)
+        }
+    }
+
+    /// left inclusive
+    /// right inclusive
+    /// Gives neutral when there are no elements
+    pub fn query(&self, left: usize, right: usize) -> T {
+        if self.data.is_empty() {
+            return self.neutral;
+        }
+
+        let left_block = self.block_of(left);
+        let right_block = self.block_of(right);
+
+        if left_block == right_block {
+            self.query_partial(left, right)
+        } else {
+            let left_end = self.block_bounds(left_block).1;
+            let right_start = self.starts[right_block];
+
+            let middle = self.blocks[left_block + 1..right_block].iter()
+                .fold(self.neutral, |res, &block| self.merge(res, block));
+
+            let res = self.merge(self.query_partial(left, left_end - 1), middle);
+            self.merge(res, self.query_partial(right_start, right))
+        }
+    }
+
+    /// Offline query(left, right) for every pair, answers are in the order of queries
+    ///
+    /// Queries are sorted by the block of left and then by right, so for a block
+    /// the fold from its end to right only grows and is reused between queries,
+    /// no inverse of merge is needed, O((n + q) sqrt n)
+    pub fn answer_queries(&self, queries: &[(usize, usize)]) -> Vec<T> {
+        for &(left, right) in queries {
+            assert!(left <= right, "range {left}..={right} is empty");
+            assert!(right < self.data.len(), "index {right} is out of range for length {}", self.data.len());
+        }
+
+        let mut order: Vec<usize> = (0..queries.len()).collect();
+        order.sort_unstable_by_key(|&query| {
+            let (left, right) = queries[query];
+            (self.block_of(left), right)
+        });
+
+        let mut answers = Vec::new();
+        answers.resize(queries.len(), self.neutral);
+
+        let mut window_block = usize::MAX;
+        let mut window = self.neutral;
+        let mut window_end = 0;
+
+        for query in order {
+            let (left, right) = queries[query];
+            let block = self.block_of(left);
+            let block_end = self.block_bounds(block).1;
+
+            if right < block_end {
+                answers[query] = self.query_partial(left, right);
+                continue;
+            }
+
+            if block != window_block {
+                window_block = block;
+                window = self.neutral;
+                window_end = block_end;
+            }
+
+            while window_end <= right {
+                window = self.merge(window, self.element(window_end));
+                window_end += 1;
+            }
+
+            answers[query] = self.merge(self.query_partial(left, block_end - 1), window);
+        }
+
+        answers
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn init_blocks(&mut self, values: &[T], block_len: usize) {
         self.data.clear();
         self.blocks.clear();
+        self.starts.clear();
+        self.delayed.clear();
 
         self.data.extend_from_slice(values);
-        self.block_len = 1 + (values.len() as f32).sqrt() as usize;
-        
+        self.block_len = block_len;
+
         for block in self.data.chunks(self.block_len) {
-            let sum = block.iter().copied().fold(T::default(), <T as Add<T>>::add);
-            self.blocks.push(sum); 
+            let folded = self.fold(block);
+            self.blocks.push(folded);
         }
+
+        self.starts.extend((0..self.blocks.len()).map(|block| block * self.block_len));
+        self.delayed.resize(self.blocks.len(), None);
     }
 
-    pub fn sum(&self, left: usize, right: usize) -> T {
+    /// Applies every tag and splits data into blocks of 1 + sqrt(len) elements
+    fn rebuild(&mut self) {
+        for block in 0..self.blocks.len() {
+            self.push(block);
+        }
+
+        let values = core::mem::take(&mut self.data);
+        self.init_blocks(&values, 1 + values.len().isqrt());
+    }
+
+    /// left and right lie in the same block
+    fn query_partial(&self, left: usize, right: usize) -> T {
+        let folded = self.fold(&self.data[left..=right]);
+
+        match self.delayed[self.block_of(left)] {
+            Some(delayed) => self.add(folded, delayed, right - left + 1),
+            None => folded,
+        }
+    }
+
+    /// Value at index with the tag of its block applied
+    fn element(&self, index: usize) -> T {
+        match self.delayed[self.block_of(index)] {
+            Some(delayed) => self.add(self.data[index], delayed, 1),
+            None => self.data[index],
+        }
+    }
+
+    /// left and right lie in the same block
+    fn add_partial(&mut self, left: usize, right: usize, delta: T) {
+        let block = self.block_of(left);
+        self.push(block);
+
+        for index in left..=right {
+            self.data[index] = self.add(self.data[index], delta, 1);
+        }
+
+        self.recalculate(block);
+    }
+
+    /// Applies the tag of block to its elements
+    fn push(&mut self, block: usize) {
+        if let Some(delayed) = self.delayed[block].take() {
+            let (start, end) = self.block_bounds(block);
+
+            for index in start..end {
+                self.data[index] = self.add(self.data[index], delayed, 1);
+            }
+        }
+    }
+
+    fn recalculate(&mut self, block: usize) {
+        let (start, end) = self.block_bounds(block);
+        self.blocks[block] = self.fold(&self.data[start..end]);
+    }
+
+    /// The last block starting at or before index, so
+    /// index = len gives the last block
+    fn block_of(&self, index: usize) -> usize {
+        self.starts.partition_point(|&start| start <= index) - 1
+    }
+
+    fn block_bounds(&self, block: usize) -> (usize, usize) {
+        let end = self.starts.get(block + 1).copied().unwrap_or(self.data.len());
+        (self.starts[block], end)
+    }
+
+    fn fold(&self, values: &[T]) -> T {
+        values.iter().fold(self.neutral, |res, &value| self.merge(res, value))
+    }
+
+    #[inline]
+    fn merge(&self, left: T, right: T) -> T {
+        (self.merge_fn)(left, right)
+    }
+
+    #[inline]
+    fn add(&self, value: T, delta: T, len: usize) -> T {
+        let add_fn = self.add_fn.as_ref().expect("add function is not set, use with_add");
+        add_fn(value, delta, len)
+    }
+}
+
+/// Same as SqrtDecomposition for values that are only Clone,
+/// merge_fn folds references, there are no range adds
+///
+/// We can not ensure this requirments with Rust
+/// (1) merge(a, neutral) = a
+/// (2) merge(merge(a, b), c) = merge(a, merge(b, c))
+pub struct RefSqrtDecomposition<T, M>
+where
+    T: Clone,
+    M: Fn(&T, &T) -> T,
+{
+    data: Vec<T>,
+    blocks: Vec<T>,
+    block_len: usize,
+    merge_fn: M,
+    neutral: T,
+}
+
+impl<T, M> RefSqrtDecomposition<T, M>
+where
+    T: Clone,
+    M: Fn(&T, &T) -> T,
+{
+    pub fn build_with(values: &[T], merge_fn: M, neutral: T) -> Self {
+        let mut res = Self {
+            data: Vec::new(),
+            blocks: Vec::new(),
+            block_len: 0,
+            merge_fn,
+            neutral,
+        };
+
+        res.init_with(values);
+        res
+    }
+
+    pub fn init_with(&mut self, values: &[T]) {
+        self.data.clear();
+        self.blocks.clear();
+
+        self.data.extend_from_slice(values);
+        self.block_len = 1 + values.len().isqrt();
+
+        for block in self.data.chunks(self.block_len) {
+            let folded = self.fold(block);
+            self.blocks.push(folded);
+        }
+    }
+
+    /// Recomputes the fold of the block containing index
+    pub fn assign(&mut self, index: usize, value: T) {
+        assert!(index < self.data.len(), "index {index} is out of range for length {}", self.data.len());
+
+        self.data[index] = value;
+        let block = index / self.block_len;
+        let start = block * self.block_len;
+        let end = (start + self.block_len).min(self.data.len());
+        self.blocks[block] = self.fold(&self.data[start..end]);
+    }
+
+    /// Empty ranges give neutral
+    pub fn query_range<R: RangeBounds<usize>>(&self, range: R) -> T {
+        let start = match range.start_bound() {
+            Bound::Included(start) => *start,
+            Bound::Excluded(before_start) => *before_start + 1,
+            Bound::Unbounded => 0,
+        };
+
+        let end = match range.end_bound() {
+            Bound::Included(end) => *end + 1,
+            Bound::Excluded(after_end) => *after_end,
+            Bound::Unbounded => self.data.len(),
+        };
+
+        if start >= end {
+            self.neutral.clone()
+        } else {
+            self.query(start, end - 1)
+        }
+    }
+
+    /// left inclusive
+    /// right inclusive
+    /// Gives neutral when there are no elements
+    pub fn query(&self, left: usize, right: usize) -> T {
+        if self.data.is_empty() {
+            return self.neutral.clone();
+        }
+
         let left_block = left / self.block_len;
         let right_block = right / self.block_len;
 
         if left_block == right_block {
-            self.data[left..=right].iter()
-                .copied()
-                .fold(T::default(), <T as Add>::add)
+            self.fold(&self.data[left..=right])
         } else {
-            let mut res = self.blocks[left_block + 1..right_block].iter()
-                .copied()
-                .fold(T::default(), <T as Add>::add);
-            
             let left_end = (left_block + 1) * self.block_len;
             let right_start = right_block * self.block_len;
 
-            res = res + self.data[left..left_end].iter()
-                .copied()
-                .fold(T::default(), <T as Add>::add);
-                
-            res = res + self.data[right_start..=right].iter()
-                .copied()
-                .fold(T::default(), <T as Add>::add);
+            let res = self.fold(&self.data[left..left_end]);
+            let res = self.blocks[left_block + 1..right_block].iter()
+                .fold(res, |res, block| (self.merge_fn)(&res, block));
+            self.data[right_start..=right].iter()
+                .fold(res, |res, value| (self.merge_fn)(&res, value))
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn fold(&self, values: &[T]) -> T {
+        values.iter().fold(self.neutral.clone(), |res, value| (self.merge_fn)(&res, value))
+    }
+}
+
+impl<T> Default for SqrtDecomposition<T>
+where
+    T: Copy + Default + Add<Output = T>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Sum of count copies of value
+fn scale<T: Copy + Default + Add<Output = T>>(value: T, count: usize) -> T {
+    let mut res = T::default();
+    let mut power = value;
+    let mut count = count;
+
+    while count > 0 {
+        if count & 1 == 1 {
+            res = res + power;
+        }
+
+        power = power + power;
+        count >>= 1;
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn assign() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let mut values: Vec<i64> = (0..50).map(|_| rng.gen_range(-100..100)).collect();
+        let mut sqrt = SqrtDecomposition::build(&values);
+
+        for _ in 0..1_000 {
+            let index = rng.gen_range(0..values.len());
+            let value = rng.gen_range(-100..100);
+            sqrt.assign(index, value);
+            values[index] = value;
+
+            let l = rng.gen_range(0..values.len());
+            let r = rng.gen_range(l..values.len());
+            assert_eq!(sqrt.sum(l, r), values[l..=r].iter().sum::<i64>(), "l: {l}, r: {r}");
+        }
+    }
+
+    #[test]
+    fn insert_remove() {
+        let mut rng = SmallRng::seed_from_u64(2);
+        let mut values: Vec<i64> = Vec::new();
+        let mut sqrt = SqrtDecomposition::new();
+
+        for _ in 0..3_000 {
+            match rng.gen_range(0..6) {
+                0..=2 => {
+                    let index = rng.gen_range(0..=values.len());
+                    let value = rng.gen_range(-100..100);
+                    sqrt.insert(index, value);
+                    values.insert(index, value);
+                }
+                3 if !values.is_empty() => {
+                    let index = rng.gen_range(0..values.len());
+                    assert_eq!(sqrt.remove(index), values.remove(index));
+                }
+                4 if !values.is_empty() => {
+                    let l = rng.gen_range(0..values.len());
+                    let r = rng.gen_range(l..values.len());
+                    let delta = rng.gen_range(-10..10);
+                    sqrt.add_range(l, r, delta);
+                    values[l..=r].iter_mut().for_each(|value| *value += delta);
+                }
+                _ => {}
+            }
+
+            assert_eq!(sqrt.len(), values.len());
+            if !values.is_empty() {
+                let l = rng.gen_range(0..values.len());
+                let r = rng.gen_range(l..values.len());
+                assert_eq!(sqrt.sum(l, r), values[l..=r].iter().sum::<i64>(), "l: {l}, r: {r}");
+            }
+        }
+
+        assert_eq!(sqrt.query_range(..), values.iter().sum::<i64>());
+        while !values.is_empty() {
+            assert_eq!(sqrt.remove(0), values.remove(0));
+            assert_eq!(sqrt.query_range(..), values.iter().sum::<i64>());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn assign_out_of_range() {
+        let mut sqrt = SqrtDecomposition::build(&[1, 2, 3]);
+        sqrt.assign(3, 4);
+    }
+
+    #[test]
+    fn add_range() {
+        let mut rng = SmallRng::seed_from_u64(3);
+
+        for len in [1, 2, 10, 50, 101] {
+            let mut values: Vec<i64> = (0..len).map(|_| rng.gen_range(-100..100)).collect();
+            let mut sqrt = SqrtDecomposition::build(&values);
+
+            for _ in 0..500 {
+                let l = rng.gen_range(0..len);
+                let r = rng.gen_range(l..len);
+
+                match rng.gen_range(0..3) {
+                    0 => {
+                        let delta = rng.gen_range(-100..100);
+                        sqrt.add_range(l, r, delta);
+                        values[l..=r].iter_mut().for_each(|value| *value += delta);
+                    }
+                    1 => {
+                        let value = rng.gen_range(-100..100);
+                        sqrt.assign(l, value);
+                        values[l] = value;
+                    }
+                    _ => assert_eq!(sqrt.sum(l, r), values[l..=r].iter().sum::<i64>(), "len: {len}, l: {l}, r: {r}"),
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "range 9..=2 is empty")]
+    fn add_range_reversed() {
+        let values: Vec<i64> = (0..16).collect();
+        let mut sqrt = SqrtDecomposition::build(&values);
+        sqrt.add_range(9, 2, 100);
+    }
+
+    #[test]
+    fn range_query() {
+        let values = [1, 3, 2, 5, 4];
+        let sqrt = SqrtDecomposition::build(&values);
+
+        assert_eq!(sqrt.query_range(..), values[..].iter().sum::<i32>());
+        assert_eq!(sqrt.query_range(1..), values[1..].iter().sum::<i32>());
+        assert_eq!(sqrt.query_range(..=3), values[..=3].iter().sum::<i32>());
+        assert_eq!(sqrt.query_range(2..4), values[2..4].iter().sum::<i32>());
+        assert_eq!(sqrt.query_range(2..2), 0);
+
+        let empty = SqrtDecomposition::<i32>::build(&[]);
+        assert_eq!(empty.query_range(..), 0);
+    }
+
+    #[test]
+    fn empty_and_single() {
+        let mut sqrt = SqrtDecomposition::<i64>::build(&[]);
+        assert_eq!(sqrt.len(), 0);
+        assert!(sqrt.is_empty());
+        assert_eq!(sqrt.sum(0, 0), 0);
+        assert_eq!(sqrt.query_range(..), 0);
+
+        sqrt.init_with(&[7]);
+        assert_eq!(sqrt.len(), 1);
+        assert!(!sqrt.is_empty());
+        assert_eq!(sqrt.sum(0, 0), 7);
+
+        sqrt.add_range(0, 0, 3);
+        assert_eq!(sqrt.query_range(..), 10);
+        sqrt.assign(0, -1);
+        assert_eq!(sqrt.query(0, 0), -1);
+    }
+
+    #[test]
+    fn block_len() {
+        let mut rng = SmallRng::seed_from_u64(4);
+        let values: Vec<i64> = (0..40).map(|_| rng.gen_range(-100..100)).collect();
+        let mut decompositions: Vec<SqrtDecomposition<i64>> = [0, 1, 2, 5, 13, 40, 1_000].iter()
+            .map(|&block_len| SqrtDecomposition::with_block_len(&values, block_len))
+            .collect();
+
+        for _ in 0..300 {
+            let l = rng.gen_range(0..values.len());
+            let r = rng.gen_range(l..values.len());
+
+            if rng.gen_bool(0.3) {
+                let delta = rng.gen_range(-100..100);
+                decompositions.iter_mut().for_each(|sqrt| sqrt.add_range(l, r, delta));
+            } else {
+                let answers: Vec<i64> = decompositions.iter().map(|sqrt| sqrt.sum(l, r)).collect();
+                assert!(answers.windows(2).all(|pair| pair[0] == pair[1]), "l: {l}, r: {r}, answers: {answers:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn ref_concatenation() {
+        let mut rng = SmallRng::seed_from_u64(5);
+
+        for len in [1, 2, 9, 10, 37] {
+            let mut values: Vec<String> = (0..len).map(|index| index.to_string()).collect();
+            let mut sqrt = RefSqrtDecomposition::build_with(&values, |a: &String, b: &String| a.clone() + b, String::new());
+
+            for _ in 0..300 {
+                let l = rng.gen_range(0..len);
+                let r = rng.gen_range(l..len);
+
+                if rng.gen_bool(0.3) {
+                    let value = "x".repeat(rng.gen_range(0..4));
+                    sqrt.assign(l, value.clone());
+                    values[l] = value;
+                } else {
+                    assert_eq!(sqrt.query(l, r), values[l..=r].concat(), "len: {len}, l: {l}, r: {r}");
+                }
+            }
+
+            assert_eq!(sqrt.query_range(..), values.concat());
+            assert_eq!(sqrt.query_range(1..1), "");
+        }
+
+        let lengths = RefSqrtDecomposition::build_with(&[vec![1, 2], vec![], vec![3]], |a: &Vec<u8>, b: &Vec<u8>| [&a[..], &b[..]].concat(), Vec::new());
+        assert_eq!(lengths.query(0, 2), [1, 2, 3]);
+        assert_eq!(lengths.len(), 3);
+    }
+
+    #[test]
+    fn answer_queries() {
+        let mut rng = SmallRng::seed_from_u64(6);
+
+        for len in [1, 8, 50, 300] {
+            let mut values: Vec<i64> = (0..len).map(|_| rng.gen_range(-100..100)).collect();
+            let mut sqrt = SqrtDecomposition::build(&values);
+
+            for _ in 0..5 {
+                let l = rng.gen_range(0..len);
+                let r = rng.gen_range(l..len);
+                let delta = rng.gen_range(-10..10);
+                sqrt.add_range(l, r, delta);
+                values[l..=r].iter_mut().for_each(|value| *value += delta);
+
+                let index = rng.gen_range(0..=len);
+                let value = rng.gen_range(-100..100);
+                sqrt.insert(index, value);
+                values.insert(index, value);
+            }
+
+            let queries: Vec<(usize, usize)> = (0..500)
+                .map(|_| {
+                    let l = rng.gen_range(0..values.len());
+                    (l, rng.gen_range(l..values.len()))
+                })
+                .collect();
+
+            let answers = sqrt.answer_queries(&queries);
+            for (&(l, r), answer) in queries.iter().zip(answers) {
+                assert_eq!(answer, sqrt.sum(l, r), "len: {len}, l: {l}, r: {r}");
+                assert_eq!(answer, values[l..=r].iter().sum::<i64>(), "len: {len}, l: {l}, r: {r}");
+            }
+        }
+
+        let digits: Vec<(u64, u64)> = (1..=7).map(|digit| (digit, 10)).collect();
+        let concat = SqrtDecomposition::build_with(&digits, |(a, pa), (b, pb)| (a * pb + b, pa * pb), (0, 1));
+        let answers: Vec<u64> = concat.answer_queries(&[(2, 6), (0, 1), (0, 6), (3, 3)]).iter()
+            .map(|&(value, _)| value)
+            .collect();
+        assert_eq!(answers, [34_567, 12, 1_234_567, 4]);
+        assert!(concat.answer_queries(&[]).is_empty());
+    }
+
+    #[test]
+    fn min_max() {
+        let mut rng = SmallRng::seed_from_u64(7);
+
+        for len in [1, 7, 64, 99] {
+            let mut values: Vec<i32> = (0..len).map(|_| rng.gen_range(-1_000..1_000)).collect();
+            let mut min = SqrtDecomposition::build_with(&values, i32::min, i32::MAX)
+                .with_add(|min, delta, _len| min + delta);
+            let mut max = SqrtDecomposition::build_with(&values, i32::max, i32::MIN);
+
+            for _ in 0..500 {
+                let l = rng.gen_range(0..len);
+                let r = rng.gen_range(l..len);
 
-            res
+                if rng.gen_bool(0.3) {
+                    let delta = rng.gen_range(-100..100);
+                    min.add_range(l, r, delta);
+                    values[l..=r].iter_mut().for_each(|value| *value += delta);
+                    (l..=r).for_each(|index| max.assign(index, values[index]));
+                } else {
+                    assert_eq!(min.query(l, r), *values[l..=r].iter().min().unwrap(), "len: {len}, l: {l}, r: {r}");
+                    assert_eq!(max.query(l, r), *values[l..=r].iter().max().unwrap(), "len: {len}, l: {l}, r: {r}");
+                }
+            }
         }
     }
 }
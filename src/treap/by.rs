@@ -0,0 +1,329 @@
+use rand::rngs::SmallRng;
+
+use alloc::vec::Vec;
+
+use core::cmp::Ordering;
+use core::fmt;
+use core::ops::{Bound, RangeBounds};
+
+use super::{Link, Node, Range, merge_nodes, split_node_by, key_node_size, for_each_key_node};
+use super::{PrioritySource, ForkSource};
+
+/// Set ordered by cmp instead of Ord, for keys that are not Ord
+/// or are wanted in another order
+///
+/// Treaps of the same type may still hold comparators with different captures,
+/// appending such treaps is memory safe, but breaks the order
+///
+/// We can not ensure this requirments with Rust
+/// (1) cmp is a total order
+pub struct TreapBy<T, F, R = SmallRng> {
+    root: Link<T>,
+    cmp: F,
+    rng: R,
+}
+
+#[cfg(feature = "std")]
+impl<T, F: Fn(&T, &T) -> Ordering> TreapBy<T, F, SmallRng> {
+    pub fn by(cmp: F) -> Self {
+        Self::with_rng(cmp, rand::SeedableRng::from_entropy())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> TreapBy<T, fn(&T, &T) -> Ordering, SmallRng> {
+    /// Ordered by key_fn(value)
+    pub fn by_key<K: Ord, E: Fn(&T) -> K + Clone>(key_fn: E) -> TreapBy<T, impl Fn(&T, &T) -> Ordering + Clone, SmallRng> {
+        TreapBy::by(move |a: &T, b: &T| key_fn(a).cmp(&key_fn(b)))
+    }
+}
+
+impl<T, F, R> TreapBy<T, F, R>
+where
+    F: Fn(&T, &T) -> Ordering,
+    R: PrioritySource<T> + ForkSource,
+{
+    pub fn with_rng(cmp: F, rng: R) -> Self {
+        Self {
+            root: None,
+            cmp,
+            rng,
+        }
+    }
+
+    /// Returns whether key was absent
+    pub fn insert(&mut self, key: T) -> bool {
+        if self.contains(&key) {
+            return false;
+        }
+
+        let (less, greater) = split_node_by(self.root.take(), &|other| self.less(other, &key));
+        let priority = self.rng.priority(&key);
+        let new_node = Node::new(key, (), priority);
+        self.root = merge_nodes(merge_nodes(less, new_node.into_root()), greater);
+        true
+    }
+
+    #[inline]
+    pub fn contains(&self, key: &T) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// The stored key equal to key under cmp
+    pub fn get(&self, key: &T) -> Option<&T> {
+        let mut node = self.root.as_deref();
+        while let Some(nd) = node {
+            match (self.cmp)(key, &nd.key) {
+                Ordering::Less => node = nd.left.as_deref(),
+                Ordering::Equal => return Some(&nd.key),
+                Ordering::Greater => node = nd.right.as_deref(),
+            }
+        }
+
+        None
+    }
+
+    /// Returns whether key was present
+    pub fn remove(&mut self, key: &T) -> bool {
+        self.take(key).is_some()
+    }
+
+    pub fn take(&mut self, key: &T) -> Option<T> {
+        let (less, rest) = split_node_by(self.root.take(), &|other| self.less(other, key));
+        let (middle, greater) = split_node_by(rest, &|other| !self.less(key, other));
+        self.root = merge_nodes(less, greater);
+        middle.map(|node| node.key)
+    }
+
+    /// Largest key less or equal than key
+    pub fn floor(&self, key: &T) -> Option<&T> {
+        let mut res = None;
+        let mut node = self.root.as_deref();
+        while let Some(nd) = node {
+            if self.less(key, &nd.key) {
+                node = nd.left.as_deref();
+            } else {
+                res = Some(&nd.key);
+                node = nd.right.as_deref();
+            }
+        }
+
+        res
+    }
+
+    /// Smallest key greater or equal than key
+    pub fn ceil(&self, key: &T) -> Option<&T> {
+        let mut res = None;
+        let mut node = self.root.as_deref();
+        while let Some(nd) = node {
+            if self.less(&nd.key, key) {
+                node = nd.right.as_deref();
+            } else {
+                res = Some(&nd.key);
+                node = nd.left.as_deref();
+            }
+        }
+
+        res
+    }
+
+    /// Appends other in O(log n), leaving other empty, keeps the rng of self,
+    /// panics if some key of other is not greater than every key of self
+    pub fn append(&mut self, other: &mut TreapBy<T, F, R>) {
+        if let (Some(last), Some(first)) = (self.iter().next_back(), other.iter().next()) {
+            assert!(self.less(last, first), "keys of other are not greater than keys of self");
+        }
+
+        self.root = merge_nodes(self.root.take(), other.root.take());
+    }
+
+    /// Keys within range in order, located in O(log n)
+    pub fn range<B: RangeBounds<T>>(&self, range: B) -> Range<'_, T> {
+        let start = range.start_bound();
+        let end = range.end_bound();
+        let after_start = |key: &T| match start {
+            Bound::Included(start) => !self.less(key, start),
+            Bound::Excluded(start) => self.less(start, key),
+            Bound::Unbounded => true,
+        };
+        let before_end = |key: &T| match end {
+            Bound::Included(end) => !self.less(end, key),
+            Bound::Excluded(end) => self.less(key, end),
+            Bound::Unbounded => true,
+        };
+
+        let mut res = Range {
+            front: Vec::new(),
+            back: Vec::new(),
+            len: 0,
+        };
+
+        let mut node = self.root.as_deref();
+        let mut not_after_start = 0;
+        while let Some(nd) = node {
+            if after_start(&nd.key) {
+                res.front.push(nd);
+                node = nd.left.as_deref();
+            } else {
+                not_after_start += key_node_size(&nd.left) + 1;
+                node = nd.right.as_deref();
+            }
+        }
+
+        let mut node = self.root.as_deref();
+        let mut before_end_count = 0;
+        while let Some(nd) = node {
+            if before_end(&nd.key) {
+                before_end_count += key_node_size(&nd.left) + 1;
+                res.back.push(nd);
+                node = nd.right.as_deref();
+            } else {
+                node = nd.left.as_deref();
+            }
+        }
+
+        res.len = before_end_count.saturating_sub(not_after_start);
+        if res.len == 0 {
+            res.front.clear();
+            res.back.clear();
+        }
+
+        res
+    }
+
+    pub fn iter(&self) -> Range<'_, T> {
+        self.range(..)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        key_node_size(&self.root)
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    #[inline]
+    fn less(&self, left: &T, right: &T) -> bool {
+        (self.cmp)(left, right) == Ordering::Less
+    }
+}
+
+impl<T, F, R> TreapBy<T, F, R>
+where
+    F: Fn(&T, &T) -> Ordering + Clone,
+    R: PrioritySource<T> + ForkSource,
+{
+    /// Returns treap with keys greater or equal than key
+    /// Left with keys less than key
+    pub fn split(&mut self, key: &T) -> TreapBy<T, F, R> {
+        let (less, greater) = split_node_by(self.root.take(), &|other| self.less(other, key));
+        self.root = less;
+        TreapBy {
+            root: greater,
+            cmp: self.cmp.clone(),
+            rng: self.rng.fork(),
+        }
+    }
+}
+
+impl<'a, T, F, R> IntoIterator for &'a TreapBy<T, F, R>
+where
+    F: Fn(&T, &T) -> Ordering,
+    R: PrioritySource<T> + ForkSource,
+{
+    type Item = &'a T;
+    type IntoIter = Range<'a, T>;
+
+    fn into_iter(self) -> Range<'a, T> {
+        self.iter()
+    }
+}
+
+/// Keys in order like a set
+impl<T: fmt::Debug, F, R> fmt::Debug for TreapBy<T, F, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut set = f.debug_set();
+        for_each_key_node(&self.root, |node| {
+            set.entry(&node.key);
+        });
+
+        set.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Player {
+        name: &'static str,
+        score: f64,
+    }
+
+    #[test]
+    fn treap_by_field_descending() {
+        let mut players = TreapBy::with_rng(
+            |a: &Player, b: &Player| b.score.total_cmp(&a.score),
+            SmallRng::seed_from_u64(1),
+        );
+
+        for (name, score) in [("ann", 3.5), ("bob", 9.0), ("cid", 1.25), ("dan", 7.0)] {
+            assert!(players.insert(Player { name, score }));
+        }
+        assert!(!players.insert(Player { name: "eve", score: 7.0 }));
+
+        let names: Vec<_> = players.iter().map(|player| player.name).collect();
+        assert_eq!(names, ["bob", "dan", "ann", "cid"]);
+
+        let probe = |score| Player { name: "", score };
+        assert_eq!(players.get(&probe(7.0)).map(|player| player.name), Some("dan"));
+        assert_eq!(players.floor(&probe(5.0)).map(|player| player.name), Some("dan"));
+        assert_eq!(players.ceil(&probe(5.0)).map(|player| player.name), Some("ann"));
+        assert_eq!(players.range(probe(8.0)..probe(1.25)).map(|player| player.name).collect::<Vec<_>>(), ["dan", "ann"]);
+
+        let mut low = players.split(&probe(5.0));
+        assert_eq!(players.len(), 2);
+        assert_eq!(low.iter().map(|player| player.name).collect::<Vec<_>>(), ["ann", "cid"]);
+        assert!(low.remove(&probe(3.5)));
+        players.append(&mut low);
+        assert!(low.is_empty());
+        assert_eq!(players.iter().rev().map(|player| player.name).collect::<Vec<_>>(), ["cid", "dan", "bob"]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn treap_by_matches_reversed_btree() {
+        use rand::Rng;
+        use std::cmp::Reverse;
+        use std::collections::BTreeSet;
+
+        let mut rng = SmallRng::seed_from_u64(2);
+        let mut treap = TreapBy::by_key(|key: &i32| Reverse(*key));
+        let mut expected = BTreeSet::new();
+
+        for _ in 0..2_000 {
+            let key = rng.gen_range(-300..300);
+            if rng.gen_bool(0.6) {
+                assert_eq!(treap.insert(key), expected.insert(Reverse(key)));
+            } else {
+                assert_eq!(treap.remove(&key), expected.remove(&Reverse(key)));
+            }
+        }
+
+        assert!(treap.iter().copied().eq(expected.iter().map(|key| key.0)));
+        let (high, low) = (rng.gen_range(0..300), rng.gen_range(-300..0));
+        assert!(treap.range(high..=low).copied().eq(expected.range(Reverse(high)..=Reverse(low)).map(|key| key.0)));
+        assert_eq!(treap.range(low..high).len(), 0);
+
+        let mut small = TreapBy::by_key(|key: &u8| Reverse(*key));
+        for key in [1, 3, 2] {
+            small.insert(key);
+        }
+        assert_eq!(format!("{small:?}"), "{3, 2, 1}");
+    }
+}
@@ -0,0 +1,43 @@
+use core::fmt;
+
+/// Returned by validate, the first broken invariant found
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InvariantViolation {
+    /// Some key is not greater than a key before it in order
+    KeysOutOfOrder,
+    PriorityOrder { parent: u64, child: u64 },
+    Size { size: usize, expected: usize },
+}
+
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::KeysOutOfOrder => write!(f, "keys are out of order"),
+            Self::PriorityOrder { parent, child } => {
+                write!(f, "child priority {child} is greater than parent priority {parent}")
+            }
+            Self::Size { size, expected } => {
+                write!(f, "node has size {size}, but its subtree has {expected} nodes")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InvariantViolation {}
+
+/// Returned by the try_* methods of ImplicitTreap
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IndexError {
+    pub index: usize,
+    pub len: usize,
+}
+
+impl fmt::Display for IndexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "index {} is out of bounds for length {}", self.index, self.len)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for IndexError {}
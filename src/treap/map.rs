@@ -0,0 +1,348 @@
+use rand::rngs::SmallRng;
+use rand::{SeedableRng, RngCore};
+
+use alloc::vec::Vec;
+
+use core::borrow::Borrow;
+use core::hash::{BuildHasher, Hash};
+
+use super::{Link, Node, merge_nodes, split_node, remove_node, key_node_size};
+use super::{insert_node, find_node, find_node_mut, first_node, last_node};
+use super::{PrioritySource, ForkSource, HashedPriorities};
+
+/// Ordered map, a treap keyed on K with a value in every node
+#[derive(Debug)]
+pub struct TreapMap<K: Ord, V, R = SmallRng> {
+    root: Link<K, V>,
+    rng: R,
+}
+
+/// Entries of a TreapMap in key order
+pub struct MapIter<'a, K: Ord, V> {
+    stack: Vec<&'a Node<K, V>>,
+}
+
+#[cfg(feature = "std")]
+impl<K: Ord, V> TreapMap<K, V, SmallRng> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K: Ord, V, R: SeedableRng> Default for TreapMap<K, V, R> {
+    fn default() -> Self {
+        let rng = R::from_entropy();
+        Self {
+            root: None,
+            rng,
+        }
+    }
+}
+
+impl<K: Ord, V, R: SeedableRng + RngCore> TreapMap<K, V, R> {
+    pub fn from_seed(seed: R::Seed) -> Self {
+        let rng = R::from_seed(seed);
+        Self {
+            root: None,
+            rng,
+        }
+    }
+}
+
+impl<K: Ord + Hash, V, H: BuildHasher> TreapMap<K, V, HashedPriorities<H>> {
+    /// Priorities are hashes of the keys, so equal sets of keys 
+    /// make equal maps, whatever the order of inserts
+    pub fn with_hashed_priorities(hasher: H) -> Self {
+        Self {
+            root: None,
+            rng: HashedPriorities::new(hasher),
+        }
+    }
+}
+
+impl<K: Ord, V, R: PrioritySource<K> + ForkSource> TreapMap<K, V, R> {
+
+    /// Returns the old value if key was already present
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some(old) = self.get_mut(&key) {
+            return Some(core::mem::replace(old, value));
+        }
+
+        let (less, greater) = split_node(self.root.take(), &key);
+        let priority = self.rng.priority(&key);
+        let new_node = Node::new(key, value, priority);
+        self.root = merge_nodes(merge_nodes(less, new_node.into_root()), greater);
+        None
+    }
+
+    /// Calls default only if key is absent, in a single descent
+    pub fn get_or_insert_with<F: FnOnce() -> V>(&mut self, key: K, default: F) -> &mut V {
+        let priority = self.rng.priority(&key);
+        insert_node(&mut self.root, key, priority, default).0
+    }
+
+    pub fn get<Q: Ord + ?Sized>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+    {
+        find_node(&self.root, key).map(|node| &node.value)
+    }
+
+    pub fn get_mut<Q: Ord + ?Sized>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+    {
+        find_node_mut(&mut self.root, key).map(|node| &mut node.value)
+    }
+
+    #[inline]
+    pub fn contains_key<Q: Ord + ?Sized>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+    {
+        self.get(key).is_some()
+    }
+
+    pub fn remove<Q: Ord + ?Sized>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+    {
+        remove_node(&mut self.root, key).map(|(_, value)| value)
+    }
+
+    /// Returns map with keys greater or equal than key
+    /// Left with keys less than key
+    pub fn split<Q: Ord + ?Sized>(&mut self, key: &Q) -> TreapMap<K, V, R>
+    where
+        K: Borrow<Q>,
+    {
+        let (less, greater) = split_node(self.root.take(), key);
+        self.root = less;
+        TreapMap {
+            root: greater,
+            rng: self.rng.fork(),
+        }
+    }
+
+    /// Appends other in O(log n), keeps the rng of self
+    ///
+    /// We can not ensure this requirments with Rust
+    /// (1) every key of self is less than every key of other
+    pub fn merge(&mut self, mut other: TreapMap<K, V, R>) {
+        debug_assert!(
+            last_node(&self.root).zip(first_node(&other.root)).is_none_or(|(last, first)| last.key < first.key),
+            "keys of self are not less than keys of other"
+        );
+
+        self.root = merge_nodes(self.root.take(), other.root.take());
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        key_node_size(&self.root)
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    pub fn iter(&self) -> MapIter<'_, K, V> {
+        MapIter::new(self.root.as_deref())
+    }
+}
+
+/// The copy gets an rng forked from a copy of the rng of self,
+/// so both maps do not draw the same priorities afterwards
+impl<K: Ord + Clone, V: Clone, R: Clone + ForkSource> Clone for TreapMap<K, V, R> {
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+            rng: self.rng.clone().fork(),
+        }
+    }
+}
+
+impl<'a, K: Ord, V, R: PrioritySource<K> + ForkSource> IntoIterator for &'a TreapMap<K, V, R> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = MapIter<'a, K, V>;
+
+    fn into_iter(self) -> MapIter<'a, K, V> {
+        self.iter()
+    }
+}
+
+impl<'a, K: Ord, V> MapIter<'a, K, V> {
+    fn new(root: Option<&'a Node<K, V>>) -> Self {
+        let mut res = Self {
+            stack: Vec::new(),
+        };
+
+        res.push_left(root);
+        res
+    }
+
+    fn push_left(&mut self, mut node: Option<&'a Node<K, V>>) {
+        while let Some(nd) = node {
+            self.stack.push(nd);
+            node = nd.left.as_deref();
+        }
+    }
+}
+
+impl<'a, K: Ord, V> Iterator for MapIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        let node = self.stack.pop()?;
+        self.push_left(node.right.as_deref());
+        Some((&node.key, &node.value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+    use std::collections::BTreeMap;
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn treap_map_insert_overwrites() {
+        let mut map = TreapMap::new();
+
+        assert_eq!(map.insert(3, "three"), None);
+        assert_eq!(map.insert(1, "one"), None);
+        assert_eq!(map.insert(3, "drei"), Some("three"));
+        assert_eq!(map.get(&3), Some(&"drei"));
+        assert_eq!(map.get(&2), None);
+
+        *map.get_mut(&1).unwrap() = "eins";
+        assert_eq!(map.remove(&1), Some("eins"));
+        assert_eq!(map.remove(&1), None);
+        assert!(!map.contains_key(&1));
+        assert!(map.contains_key(&3));
+    }
+
+    #[test]
+    fn treap_map_get_or_insert_with() {
+        let mut map = TreapMap::<&str, u32, SmallRng>::from_seed([9; 32]);
+
+        *map.get_or_insert_with("a", || 0) += 1;
+        *map.get_or_insert_with("b", || 10) += 1;
+        *map.get_or_insert_with("a", || unreachable!()) += 1;
+
+        assert_eq!(map.get(&"a"), Some(&2));
+        assert_eq!(map.get(&"b"), Some(&11));
+        assert_eq!(map.len(), 2);
+
+        let mut rng = SmallRng::seed_from_u64(1);
+        let mut expected = BTreeMap::new();
+        let mut counts = TreapMap::<u32, u32, SmallRng>::from_seed([10; 32]);
+        for _ in 0..3_000 {
+            let key = rng.gen_range(0..300);
+            *counts.get_or_insert_with(key, || 0) += 1;
+            *expected.entry(key).or_insert(0) += 1;
+        }
+
+        assert_eq!(counts.len(), expected.len());
+        assert!(counts.iter().eq(expected.iter()));
+    }
+
+    #[test]
+    fn treap_map_hashed_priorities() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::BuildHasherDefault;
+
+        let hasher = BuildHasherDefault::<DefaultHasher>::default();
+        let mut first = TreapMap::with_hashed_priorities(hasher.clone());
+        let mut second = TreapMap::with_hashed_priorities(hasher);
+        for key in 0..500 {
+            first.insert(key, key * 2);
+            second.insert(499 - key, (499 - key) * 2);
+        }
+
+        assert_eq!(format!("{first:?}"), format!("{second:?}"));
+        let greater = second.split(&250);
+        assert_eq!(greater.len(), 250);
+    }
+
+    #[test]
+    fn treap_map_ordered_iteration() {
+        let mut rng = SmallRng::seed_from_u64(2);
+        let mut map = TreapMap::<i32, i32, SmallRng>::from_seed([7; 32]);
+        let mut expected = BTreeMap::new();
+
+        for _ in 0..2_000 {
+            let key = rng.gen_range(-500..500);
+            let value = rng.gen();
+
+            if rng.gen_bool(0.7) {
+                assert_eq!(map.insert(key, value), expected.insert(key, value));
+            } else {
+                assert_eq!(map.remove(&key), expected.remove(&key));
+            }
+        }
+
+        assert!(map.iter().eq(expected.iter()));
+    }
+
+    #[test]
+    fn treap_map_clone_draws_other_priorities() {
+        let mut map = TreapMap::<u32, u32, SmallRng>::from_seed([14; 32]);
+        map.insert(0, 0);
+        let mut copy = map.clone();
+
+        for key in 1..100 {
+            map.insert(key, key);
+            copy.insert(key, key);
+        }
+
+        assert!(map.iter().eq(copy.iter()));
+        assert_ne!(map.root.as_ref().map(|root| root.priority), copy.root.as_ref().map(|root| root.priority));
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "keys of self are not less than keys of other")]
+    fn treap_map_merge_unordered() {
+        let mut map = TreapMap::<u32, &str, SmallRng>::from_seed([12; 32]);
+        let mut other = TreapMap::<u32, &str, SmallRng>::from_seed([13; 32]);
+        map.insert(5, "five");
+        other.insert(5, "cinq");
+        map.merge(other);
+    }
+
+    #[test]
+    fn treap_map_split_merge() {
+        let mut rng = SmallRng::seed_from_u64(3);
+        let mut map = TreapMap::<i32, i32, SmallRng>::from_seed([8; 32]);
+        let mut expected = BTreeMap::new();
+
+        for _ in 0..2_000 {
+            let key = rng.gen_range(-500..500);
+            let value = rng.gen();
+
+            match rng.gen_range(0..4) {
+                0 | 1 => assert_eq!(map.insert(key, value), expected.insert(key, value)),
+                2 => assert_eq!(map.remove(&key), expected.remove(&key)),
+                _ => {
+                    let greater = map.split(&key);
+                    let expected_greater = expected.split_off(&key);
+                    assert_eq!(greater.len(), expected_greater.len());
+                    assert!(greater.iter().eq(expected_greater.iter()));
+
+                    map.merge(greater);
+                    expected.extend(expected_greater);
+                }
+            }
+
+            assert_eq!(map.len(), expected.len());
+        }
+
+        assert!(map.iter().eq(expected.iter()));
+        assert_eq!(map.split(&i32::MIN).len(), expected.len());
+        assert!(map.is_empty());
+    }
+}
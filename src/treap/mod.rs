@@ -0,0 +1,4374 @@
+mod by;
+mod error;
+mod map;
+mod multiset;
+mod persistent;
+mod priority;
+
+pub use by::TreapBy;
+pub use error::{InvariantViolation, IndexError};
+pub use map::{TreapMap, MapIter};
+pub use multiset::{TreapMultiset, MultisetIter};
+pub use persistent::PersistentImplicitTreap;
+pub use priority::{PrioritySource, ForkSource, HashedPriorities};
+
+use rand::rngs::SmallRng;
+use rand::{SeedableRng, RngCore};
+
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use core::borrow::Borrow;
+use core::cell::{Cell, OnceCell};
+use core::cmp::Ordering;
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::ops::{Bound, Index, IndexMut, RangeBounds};
+
+pub struct Treap<K: Ord, R = SmallRng> {
+    root: Link<K>,
+    rng: R, 
+}
+
+pub struct ImplicitTreap<T, R = SmallRng> {
+    root: ImplicitLink<T>,
+    rng: R, 
+    ops: Ops<T>,
+}
+
+#[derive(Clone, Debug)]
+struct ImplicitNode<T> {
+    value: T,
+    /// Fold of the whole subtree, `None` while it is just `value`
+    aggregate: Option<T>,
+    size: usize,
+    priority: u64,
+    /// Children are yet to be swapped and reversed
+    reversed: bool,
+    /// Add applied to value and aggregate, but not to children yet
+    delayed: Option<T>,
+    /// A value of the subtree was changed in place,
+    /// so aggregate is recomputed by the next update of the parent
+    stale: bool,
+    /// Value with the pending adds of the ancestors, filled by reads through &self
+    /// and dropped when a new add reaches the subtree
+    resolved: OnceCell<T>,
+    /// Some node of the subtree may have resolved filled
+    cached: Cell<bool>,
+    left: ImplicitLink<T>,
+    right: ImplicitLink<T>,
+
+}
+
+/// Functions the nodes of an `ImplicitTreap` are maintained with
+#[derive(Debug)]
+struct Ops<T> {
+    merge_fn: Option<fn(&T, &T) -> T>,
+    lazy_fn: Option<fn(&T, &T, usize) -> T>,
+    /// Lets pending adds be copied to both children without T: Clone on every method
+    clone_fn: Option<fn(&T) -> T>,
+}
+
+/// Values of an ImplicitTreap in positional order
+pub struct Iter<'a, T> {
+    nodes: ResolvedNodes<'a, T>,
+    len: usize,
+}
+
+/// Mutable values of an ImplicitTreap in positional order
+pub struct IterMut<'a, T> {
+    /// Values not yielded yet with the right subtrees after them
+    stack: Vec<(&'a mut T, Option<&'a mut ImplicitNode<T>>)>,
+    ops: Ops<T>,
+    len: usize,
+}
+
+/// Owned values of an ImplicitTreap in positional order
+pub struct ImplicitIntoIter<T> {
+    /// Nodes whose value and right subtree are not yielded yet,
+    /// left subtrees are already on the stack
+    stack: Vec<Box<ImplicitNode<T>>>,
+    ops: Ops<T>,
+    len: usize,
+}
+
+/// Keys of a Treap or a TreapBy within bounds in ascending order
+pub struct Range<'a, K> {
+    /// Leftmost path of the keys not yielded from the front
+    front: Vec<&'a Node<K>>,
+    /// Rightmost path of the keys not yielded from the back
+    back: Vec<&'a Node<K>>,
+    len: usize,
+}
+
+/// Owned keys of a Treap in ascending order
+pub struct IntoIter<K: Ord> {
+    /// Nodes whose key and right subtree are not yielded yet,
+    /// left subtrees are already on the stack
+    stack: Vec<Box<Node<K>>>,
+    len: usize,
+}
+
+/// Keys removed by Treap::drain_range, they are removed even if it is dropped early
+pub struct Drain<K: Ord> {
+    inner: IntoIter<K>,
+}
+
+/// In order walk of ImplicitNodes, which reads reversals and pending adds
+/// without pushing them down, yields every node with its value with the
+/// pending adds of its ancestors applied
+struct ResolvedNodes<'a, T> {
+    /// Nodes with the parity of reversals and the pending adds above them
+    stack: Vec<(&'a ImplicitNode<T>, bool, Option<T>)>,
+    ops: Ops<T>,
+}
+
+/// Entry of the alternate Debug output of the treaps
+struct DebugNode<'a, T> {
+    name: &'static str,
+    value: &'a T,
+    size: usize,
+    priority: u64,
+}
+
+type Link<K, V = ()> = Option<Box<Node<K, V>>>;
+type ImplicitLink<T> = Option<Box<ImplicitNode<T>>>;
+
+/// Nodes of a `Treap` carry no value, nodes of a `TreapMap` do
+#[derive(Clone, Debug)]
+struct Node<K, V = ()> {
+    key: K,
+    value: V,
+    size: usize,
+    /// 64 bits make equal priorities unlikely, those still keep the heap order,
+    /// the node inserted first stays above
+    priority: u64,
+    left: Link<K, V>,
+    right: Link<K, V>,
+}
+
+/// O(log n) if every key of left is less than every key of right,
+/// otherwise falls back to union, the result keeps the rng of left
+pub fn merge<K: Ord, R: PrioritySource<K>>(left: Treap<K, R>, right: Treap<K, R>) 
+-> Treap<K, R> {
+    let ordered = match (left.last(), right.first()) {
+        (Some(last), Some(first)) => last < first,
+        _ => true,
+    };
+
+    if ordered {
+        merge_unchecked(left, right)
+    } else {
+        left.union(right)
+    }
+}
+
+/// O(log n), the result keeps the rng of left
+///
+/// We can not ensure this requirments with Rust
+/// (1) every key of left is less than every key of right
+pub fn merge_unchecked<K: Ord, R: PrioritySource<K>>(mut left: Treap<K, R>, mut right: Treap<K, R>) 
+-> Treap<K, R> {
+    debug_assert!(
+        left.last().zip(right.first()).is_none_or(|(last, first)| last < first),
+        "keys of left are not less than keys of right"
+    );
+
+    let root = merge_nodes(left.root.take(), right.root.take());
+    Treap::from_root(root, left.rng)
+}
+
+#[cfg(feature = "std")]
+impl<K: Ord> Treap<K, SmallRng> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K: Ord, R: SeedableRng> Default for Treap<K, R> {
+    fn default() -> Self {
+        let rng = R::from_entropy();
+        Self {
+            root: None,
+            rng,
+        }
+    } 
+}
+
+impl<K: Ord, R: SeedableRng + RngCore> Treap<K, R> {
+    pub fn from_seed(seed: R::Seed) -> Self {
+        Self::with_rng(R::from_seed(seed))
+    }
+}
+
+impl<K: Ord + Hash, H: BuildHasher> Treap<K, HashedPriorities<H>> {
+    /// Priorities are hashes of the keys, so equal sets of keys 
+    /// make equal treaps, whatever the order of inserts
+    pub fn with_hashed_priorities(hasher: H) -> Self {
+        Self::with_rng(HashedPriorities::new(hasher))
+    }
+}
+
+/// Only what needs new priorities, like the other half of a split
+impl<K: Ord, R: PrioritySource<K> + ForkSource> Treap<K, R> {
+    /// Returns treap with keys greater or equal than key
+    /// Left with keys less than key
+    pub fn split<Q: Ord + ?Sized>(&mut self, key: &Q) -> Treap<K, R>
+    where
+        K: Borrow<Q>,
+    {
+        let rng = self.rng.fork();
+        self.split_with(key, rng)
+    }
+
+    /// Leaves the k smallest keys in self and returns the rest, in O(log n)
+    pub fn split_at_rank(&mut self, k: usize) -> Treap<K, R> {
+        let rng = self.rng.fork();
+        self.split_at_rank_with(k, rng)
+    }
+
+    /// left inclusive
+    /// right exclusive
+    /// Same as remove_range, but returns the removed keys as a treap, in O(log n)
+    pub fn extract_range<Q: Ord + ?Sized>(&mut self, left: &Q, right: &Q) -> Treap<K, R>
+    where
+        K: Borrow<Q>,
+    {
+        let rng = self.rng.fork();
+        self.extract_range_with(left, right, rng)
+    }
+}
+
+impl<K: Ord, R: PrioritySource<K>> Treap<K, R> {
+    /// Takes a ready rng, e.g. one seeded elsewhere, a mock or a borrowed &mut rng
+    pub fn with_rng(rng: R) -> Self {
+        Self {
+            root: None,
+            rng,
+        }
+    }
+
+    /// Same as split, but the returned treap draws its priorities from rng,
+    /// so treaps whose rng can not be forked can be split too
+    pub fn split_with<Q: Ord + ?Sized, S: PrioritySource<K>>(&mut self, key: &Q, rng: S) -> Treap<K, S>
+    where
+        K: Borrow<Q>,
+    {
+        let (less, greater) = split_node(self.root.take(), key);
+        self.root = less;
+        Treap::from_root(greater, rng)
+    }
+
+    /// Same as split_at_rank, but the returned treap draws its priorities from rng
+    pub fn split_at_rank_with<S: PrioritySource<K>>(&mut self, k: usize, rng: S) -> Treap<K, S> {
+        let (less, greater) = split_node_at(self.root.take(), k);
+        self.root = less;
+        Treap::from_root(greater, rng)
+    }
+
+    /// Same as extract_range, but the returned treap draws its priorities from rng
+    pub fn extract_range_with<Q: Ord + ?Sized, S: PrioritySource<K>>(&mut self, left: &Q, right: &Q, rng: S) -> Treap<K, S>
+    where
+        K: Borrow<Q>,
+    {
+        assert!(left <= right);
+        let (less, rest) = split_node(self.root.take(), left);
+        let (middle, greater) = split_node(rest, right);
+        self.root = merge_nodes(less, greater);
+        Treap::from_root(middle, rng)
+    }
+
+    /// Replaces the content with keys in O(n), keys must be sorted,
+    /// equal keys are kept once
+    pub fn init_with_sorted<I: IntoIterator<Item = K>>(&mut self, keys: I) {
+        // right spine of the cartesian tree built so far
+        let mut stack: Vec<Box<Node<K>>> = Vec::new();
+
+        for key in keys {
+            if let Some(top) = stack.last() {
+                debug_assert!(top.key <= key, "keys are not sorted");
+                if top.key == key {
+                    continue;
+                }
+            }
+
+            let priority = self.rng.priority(&key);
+            let mut node = Box::new(Node::new(key, (), priority));
+            let mut last = None;
+
+            while stack.last().is_some_and(|top| top.priority < node.priority) {
+                let mut top = stack.pop().unwrap();
+                top.right = last;
+                top.update_size();
+                last = Some(top);
+            }
+
+            node.left = last;
+            stack.push(node);
+        }
+
+        self.root = stack.into_iter().rev().fold(None, |last, mut top| {
+            top.right = last;
+            top.update_size();
+            Some(top)
+        });
+    }
+
+    /// Like BTreeSet::insert, returns false and keeps the treap as is
+    /// if key is already present
+    pub fn insert(&mut self, key: K) -> bool { 
+        self.insert_if_absent(key)
+    }
+
+    /// Returns whether key was inserted, in a single descent
+    pub fn insert_if_absent(&mut self, key: K) -> bool {
+        let priority = self.rng.priority(&key);
+        insert_node(&mut self.root, key, priority, || ()).1
+    }
+    
+    pub fn contains<Q: Ord + ?Sized>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+    {
+        self.get(key).is_some()
+    }
+
+    /// The stored key equal to key
+    pub fn get<Q: Ord + ?Sized>(&self, key: &Q) -> Option<&K>
+    where
+        K: Borrow<Q>,
+    {
+        find_node(&self.root, key).map(|node| &node.key)
+    }
+    
+    /// left inclusive
+    /// right exclusive
+    pub fn remove_range<Q: Ord + ?Sized>(&mut self, left: &Q, right: &Q)
+    where
+        K: Borrow<Q>,
+    {
+        assert!(left <= right);
+        let (less, rest) = split_node(self.root.take(), left);
+        let (_, greater) = split_node(rest, right);
+        self.root = merge_nodes(less, greater);
+    }
+
+    /// Replaces the keys in left..right by new_keys,
+    /// panics if some of new_keys is out of left..right
+    pub fn replace_range<Q: Ord + ?Sized, I: IntoIterator<Item = K>>(&mut self, left: &Q, right: &Q, new_keys: I)
+    where
+        K: Borrow<Q>,
+    {
+        assert!(left <= right, "replaced range is reversed");
+        let mut middle = None;
+        for key in new_keys {
+            assert!(left <= key.borrow() && key.borrow() < right, "new keys are out of the replaced range");
+            let priority = self.rng.priority(&key);
+            insert_node(&mut middle, key, priority, || ());
+        }
+
+        let (less, rest) = split_node(self.root.take(), left);
+        let (_, greater) = split_node(rest, right);
+        self.root = merge_nodes(merge_nodes(less, middle), greater);
+    }
+
+    /// Keeps only the k smallest keys
+    pub fn truncate_to_smallest(&mut self, k: usize) {
+        let (less, _) = split_node_at(self.root.take(), k);
+        self.root = less;
+    }
+
+    /// Drops every key, keeps the rng
+    pub fn clear(&mut self) {
+        self.root = None;
+    }
+
+    /// Keeps the keys with f(key) in O(n), kept nodes are relinked
+    /// in order with their priorities, like in init_with_sorted
+    pub fn retain<F: FnMut(&K) -> bool>(&mut self, mut f: F) {
+        let mut pending = Vec::new();
+        // right spine of the cartesian tree built so far
+        let mut stack: Vec<Box<Node<K>>> = Vec::new();
+        let mut next = self.root.take();
+
+        loop {
+            while let Some(mut nd) = next {
+                next = nd.left.take();
+                pending.push(nd);
+            }
+
+            let Some(mut node) = pending.pop() else {
+                break;
+            };
+
+            next = node.right.take();
+            if !f(&node.key) {
+                continue;
+            }
+
+            let mut last = None;
+            while stack.last().is_some_and(|top| top.priority < node.priority) {
+                let mut top = stack.pop().unwrap();
+                top.right = last;
+                top.update_size();
+                last = Some(top);
+            }
+
+            node.left = last;
+            stack.push(node);
+        }
+
+        self.root = stack.into_iter().rev().fold(None, |last, mut top| {
+            top.right = last;
+            top.update_size();
+            Some(top)
+        });
+    }
+
+    /// Returns whether key was present
+    pub fn remove<Q: Ord + ?Sized>(&mut self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+    {
+        remove_node(&mut self.root, key).is_some()
+    }
+
+    /// Removes the stored key equal to key and returns it
+    pub fn take<Q: Ord + ?Sized>(&mut self, key: &Q) -> Option<K>
+    where
+        K: Borrow<Q>,
+    {
+        remove_node(&mut self.root, key).map(|(key, _)| key)
+    }
+
+    /// Smallest key
+    pub fn first(&self) -> Option<&K> {
+        first_node(&self.root).map(|node| &node.key)
+    }
+
+    /// Largest key
+    pub fn last(&self) -> Option<&K> {
+        last_node(&self.root).map(|node| &node.key)
+    }
+
+    pub fn pop_first(&mut self) -> Option<K> {
+        pop_first_node(&mut self.root).map(|node| node.key)
+    }
+
+    pub fn pop_last(&mut self) -> Option<K> {
+        pop_last_node(&mut self.root).map(|node| node.key)
+    }
+
+    /// Largest key less or equal than key
+    pub fn floor<Q: Ord + ?Sized>(&self, key: &Q) -> Option<&K>
+    where
+        K: Borrow<Q>,
+    {
+        self.below(key, true)
+    }
+
+    /// Smallest key greater or equal than key
+    pub fn ceil<Q: Ord + ?Sized>(&self, key: &Q) -> Option<&K>
+    where
+        K: Borrow<Q>,
+    {
+        self.above(key, true)
+    }
+
+    /// Largest key less than key
+    pub fn lower<Q: Ord + ?Sized>(&self, key: &Q) -> Option<&K>
+    where
+        K: Borrow<Q>,
+    {
+        self.below(key, false)
+    }
+
+    /// Smallest key greater than key
+    pub fn higher<Q: Ord + ?Sized>(&self, key: &Q) -> Option<&K>
+    where
+        K: Borrow<Q>,
+    {
+        self.above(key, false)
+    }
+
+    /// k-th smallest key, 0-indexed
+    pub fn kth(&self, mut k: usize) -> Option<&K> {
+        let mut node = self.root.as_deref();
+        while let Some(nd) = node {
+            let left_size = key_node_size(&nd.left);
+            node = match k.cmp(&left_size) {
+                Ordering::Less => nd.left.as_deref(),
+                Ordering::Equal => return Some(&nd.key),
+                Ordering::Greater => {
+                    k -= left_size + 1;
+                    nd.right.as_deref()
+                }
+            };
+        }
+
+        None
+    }
+
+    /// Number of keys less than key, key does not have to be present
+    pub fn rank<Q: Ord + ?Sized>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+    {
+        self.count_below(key, false)
+    }
+
+    /// left inclusive
+    /// right exclusive
+    /// Number of keys in the range, 0 if left >= right
+    pub fn count_range<Q: Ord + ?Sized>(&self, left: &Q, right: &Q) -> usize
+    where
+        K: Borrow<Q>,
+    {
+        if left >= right {
+            return 0;
+        }
+
+        self.rank(right) - self.rank(left)
+    }
+
+    /// Same as count_range, but for any range of keys
+    pub fn count_in<Q: Ord + ?Sized, B: RangeBounds<Q>>(&self, range: B) -> usize
+    where
+        K: Borrow<Q>,
+    {
+        let before_start = match range.start_bound() {
+            Bound::Included(start) => self.count_below(start, false),
+            Bound::Excluded(start) => self.count_below(start, true),
+            Bound::Unbounded => 0,
+        };
+
+        let before_end = match range.end_bound() {
+            Bound::Included(end) => self.count_below(end, true),
+            Bound::Excluded(end) => self.count_below(end, false),
+            Bound::Unbounded => self.len(),
+        };
+
+        before_end.saturating_sub(before_start)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        key_node_size(&self.root)
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Checks key order, heap order of priorities and sizes of every node
+    pub fn check_invariants(&self) -> Result<(), String> {
+        check_key_nodes(&self.root).map_err(|err| err.to_string())
+    }
+
+    /// Same as check_invariants
+    #[cfg(any(test, feature = "validate"))]
+    pub fn validate(&self) -> Result<(), InvariantViolation> {
+        check_key_nodes(&self.root)
+    }
+
+    /// Detaches the keys within range in O(log n), the rest stays in self
+    pub fn drain_range<Q: Ord + ?Sized, B: RangeBounds<Q>>(&mut self, range: B) -> Drain<K>
+    where
+        K: Borrow<Q>,
+    {
+        let (less, rest) = split_node_by(self.root.take(), &|key: &K| !after_start(key, range.start_bound()));
+        let (middle, greater) = split_node_by(rest, &|key: &K| before_end(key, range.end_bound()));
+        self.root = merge_nodes(less, greater);
+
+        Drain {
+            inner: IntoIter::new(middle),
+        }
+    }
+
+    /// Moves every key of other into self, leaving other empty, keeps the rng of self
+    /// O(log n) if every key of self is less than every key of other,
+    /// otherwise falls back to union like merge
+    pub fn append(&mut self, other: &mut Treap<K, R>) {
+        let ordered = match (self.last(), other.first()) {
+            (Some(last), Some(first)) => last < first,
+            _ => true,
+        };
+
+        self.root = if ordered {
+            merge_nodes(self.root.take(), other.root.take())
+        } else {
+            union_nodes(self.root.take(), other.root.take())
+        };
+    }
+
+    /// Keys in any, in O(m log(n / m)) for sizes m <= n, keeps the rng of self
+    pub fn union(mut self, mut other: Treap<K, R>) -> Treap<K, R> {
+        self.root = union_nodes(self.root.take(), other.root.take());
+        self
+    }
+
+    /// Keys in both, keeps the rng of self
+    pub fn intersection(mut self, mut other: Treap<K, R>) -> Treap<K, R> {
+        self.root = intersect_nodes(self.root.take(), other.root.take());
+        self
+    }
+
+    /// Keys of self not in other, keeps the rng of self
+    pub fn difference(mut self, mut other: Treap<K, R>) -> Treap<K, R> {
+        self.root = difference_nodes(self.root.take(), other.root.take());
+        self
+    }
+
+    /// Walks both in order, O(n + m)
+    pub fn is_subset(&self, other: &Treap<K, R>) -> bool {
+        let mut other_keys = other.range(..).peekable();
+        self.range(..).all(|key| {
+            while other_keys.next_if(|other_key| *other_key < key).is_some() {}
+            other_keys.next_if_eq(&key).is_some()
+        })
+    }
+
+    /// Walks both in order, O(n + m)
+    pub fn is_disjoint(&self, other: &Treap<K, R>) -> bool {
+        let mut other_keys = other.range(..).peekable();
+        self.range(..).all(|key| {
+            while other_keys.next_if(|other_key| *other_key < key).is_some() {}
+            other_keys.peek() != Some(&key)
+        })
+    }
+
+    /// Locates both ends in O(log n), every next is O(1) amortized
+    pub fn range<Q: Ord + ?Sized, B: RangeBounds<Q>>(&self, range: B) -> Range<'_, K>
+    where
+        K: Borrow<Q>,
+    {
+        let len = self.count_in((range.start_bound(), range.end_bound()));
+        let mut res = Range {
+            front: Vec::new(),
+            back: Vec::new(),
+            len,
+        };
+
+        if len == 0 {
+            return res;
+        }
+
+        let mut node = self.root.as_deref();
+        while let Some(nd) = node {
+            if after_start(&nd.key, range.start_bound()) {
+                res.front.push(nd);
+                node = nd.left.as_deref();
+            } else {
+                node = nd.right.as_deref();
+            }
+        }
+
+        let mut node = self.root.as_deref();
+        while let Some(nd) = node {
+            if before_end(&nd.key, range.end_bound()) {
+                res.back.push(nd);
+                node = nd.right.as_deref();
+            } else {
+                node = nd.left.as_deref();
+            }
+        }
+
+        res
+    }
+
+    /// Largest key less than key, or less or equal if inclusive
+    fn below<Q: Ord + ?Sized>(&self, key: &Q, inclusive: bool) -> Option<&K>
+    where
+        K: Borrow<Q>,
+    {
+        let mut res = None;
+        let mut node = self.root.as_deref();
+        while let Some(nd) = node {
+            if nd.key.borrow() < key || (inclusive && nd.key.borrow() == key) {
+                res = Some(&nd.key);
+                node = nd.right.as_deref();
+            } else {
+                node = nd.left.as_deref();
+            }
+        }
+
+        res
+    }
+
+    /// Smallest key greater than key, or greater or equal if inclusive
+    fn above<Q: Ord + ?Sized>(&self, key: &Q, inclusive: bool) -> Option<&K>
+    where
+        K: Borrow<Q>,
+    {
+        let mut res = None;
+        let mut node = self.root.as_deref();
+        while let Some(nd) = node {
+            if nd.key.borrow() > key || (inclusive && nd.key.borrow() == key) {
+                res = Some(&nd.key);
+                node = nd.left.as_deref();
+            } else {
+                node = nd.right.as_deref();
+            }
+        }
+
+        res
+    }
+
+    /// Number of keys less than key, or less or equal if inclusive
+    fn count_below<Q: Ord + ?Sized>(&self, key: &Q, inclusive: bool) -> usize
+    where
+        K: Borrow<Q>,
+    {
+        let mut res = 0;
+        let mut node = self.root.as_deref();
+        while let Some(nd) = node {
+            if nd.key.borrow() < key || (inclusive && nd.key.borrow() == key) {
+                res += key_node_size(&nd.left) + 1;
+                node = nd.right.as_deref();
+            } else {
+                node = nd.left.as_deref();
+            }
+        }
+
+        res
+    }
+
+    fn from_root(root: Link<K>, rng: R) -> Self {
+        Self {
+            root,
+            rng,
+        }
+    }
+}
+
+impl<'a, K> Iterator for Range<'a, K> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<&'a K> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let node = self.front.pop()?;
+        let mut next = node.right.as_deref();
+        while let Some(nd) = next {
+            self.front.push(nd);
+            next = nd.left.as_deref();
+        }
+
+        self.len -= 1;
+        Some(&node.key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, K> DoubleEndedIterator for Range<'a, K> {
+    fn next_back(&mut self) -> Option<&'a K> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let node = self.back.pop()?;
+        let mut next = node.left.as_deref();
+        while let Some(nd) = next {
+            self.back.push(nd);
+            next = nd.right.as_deref();
+        }
+
+        self.len -= 1;
+        Some(&node.key)
+    }
+}
+
+impl<'a, K> ExactSizeIterator for Range<'a, K> {}
+
+/// The copy gets an rng forked from a copy of the rng of self,
+/// so both treaps do not draw the same priorities afterwards
+impl<K: Ord + Clone, R: Clone + ForkSource> Clone for Treap<K, R> {
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+            rng: self.rng.clone().fork(),
+        }
+    }
+}
+
+/// Same keys in order, whatever the shapes, O(n)
+impl<K: Ord, R: PrioritySource<K>> PartialEq for Treap<K, R> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.range::<K, _>(..).eq(other.range::<K, _>(..))
+    }
+}
+
+impl<K: Ord, R: PrioritySource<K>> Eq for Treap<K, R> {}
+
+/// Keys in order like a set, {:#?} also shows the size and priority of every node
+impl<K: Ord + fmt::Debug, R> fmt::Debug for Treap<K, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let alternate = f.alternate();
+        let mut set = f.debug_set();
+
+        for_each_key_node(&self.root, |node| {
+            if alternate {
+                set.entry(&DebugNode { name: "key", value: &node.key, size: node.size, priority: node.priority });
+            } else {
+                set.entry(&node.key);
+            }
+        });
+
+        set.finish()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K: Ord, R: SeedableRng + RngCore> FromIterator<K> for Treap<K, R> {
+    fn from_iter<I: IntoIterator<Item = K>>(keys: I) -> Self {
+        let mut res = Self::default();
+        res.extend(keys);
+        res
+    }
+}
+
+/// Inserts one by one in O(log n) each, use init_with_sorted for sorted keys
+impl<K: Ord, R: PrioritySource<K>> Extend<K> for Treap<K, R> {
+    fn extend<I: IntoIterator<Item = K>>(&mut self, keys: I) {
+        for key in keys {
+            self.insert(key);
+        }
+    }
+}
+
+impl<K: Ord, R: PrioritySource<K>> IntoIterator for Treap<K, R> {
+    type Item = K;
+    type IntoIter = IntoIter<K>;
+
+    fn into_iter(mut self) -> IntoIter<K> {
+        IntoIter::new(self.root.take())
+    }
+}
+
+impl<K: Ord> IntoIter<K> {
+    fn new(root: Link<K>) -> Self {
+        let mut res = Self {
+            stack: Vec::new(),
+            len: key_node_size(&root),
+        };
+
+        res.push_left(root);
+        res
+    }
+
+    fn push_left(&mut self, mut node: Link<K>) {
+        while let Some(mut nd) = node {
+            node = nd.left.take();
+            self.stack.push(nd);
+        }
+    }
+}
+
+impl<K: Ord> Iterator for IntoIter<K> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<K> {
+        let mut node = self.stack.pop()?;
+        self.push_left(node.right.take());
+        self.len -= 1;
+        Some(node.key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<K: Ord> ExactSizeIterator for IntoIter<K> {}
+
+/// Drops node by node, so deep treaps can not overflow the stack
+impl<K: Ord> Drop for IntoIter<K> {
+    fn drop(&mut self) {
+        while self.next().is_some() {}
+    }
+}
+
+impl<K: Ord> Iterator for Drain<K> {
+    type Item = K;
+
+    #[inline]
+    fn next(&mut self) -> Option<K> {
+        self.inner.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K: Ord> ExactSizeIterator for Drain<K> {}
+
+/// The result keeps the functions of `left`
+pub fn merge_implicit<T, R: RngCore>(mut left: ImplicitTreap<T, R>, 
+    mut right: ImplicitTreap<T, R>) -> ImplicitTreap<T, R> 
+{
+    let root = merge_implicit_nodes(left.root.take(), right.root.take(), left.ops);
+    ImplicitTreap::from_root(root, left.ops, left.rng)
+}
+
+#[cfg(feature = "std")]
+impl<T, R: SeedableRng> Default for ImplicitTreap<T, R> {
+    fn default() -> Self {
+        let rng = R::from_entropy();
+
+        Self {
+            root: None,
+            rng,
+            ops: Ops::default(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> ImplicitTreap<T, SmallRng> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn build(values: &[T]) -> Self 
+    where
+        T: Clone,
+    {
+        Self::from_slice(values)
+    }
+}
+
+/// Only what needs a new rng, like the other half of a split
+impl<T, R: SeedableRng + RngCore> ImplicitTreap<T, R> {
+    pub fn from_seed(seed: R::Seed) -> Self {
+        Self::with_rng(R::from_seed(seed))
+    }
+
+    /// Builds the treap in O(n)
+    #[cfg(feature = "std")]
+    pub fn from_slice(values: &[T]) -> Self 
+    where
+        T: Clone,
+    {
+        let mut res = Self::default();
+        res.init_with(values);
+        res
+    }
+
+    pub fn split(&mut self, index: usize) -> ImplicitTreap<T, R> {
+        let rng = fork_rng(&mut self.rng);
+        self.split_with(index, rng)
+    }
+
+    /// Returns [index..] as a new treap and leaves [..index] in self, like Vec::split_off
+    pub fn split_off(&mut self, index: usize) -> ImplicitTreap<T, R> {
+        self.split(index)
+    }
+
+    /// left inclusive
+    /// right exclusive
+    /// Same as remove_range, but returns the removed values as a treap,
+    /// moves a subarray in O(log n) together with split_off and append
+    pub fn extract_range(&mut self, left: usize, right: usize) -> ImplicitTreap<T, R> {
+        let rng = fork_rng(&mut self.rng);
+        self.extract_range_with(left, right, rng)
+    }
+
+    /// Consumes the treap into [..index] and [index..], 
+    /// the first half keeps the rng
+    pub fn into_split(mut self, index: usize) -> (ImplicitTreap<T, R>, ImplicitTreap<T, R>) {
+        let greater = self.split_off(index);
+        (self, greater)
+    }
+}
+
+impl<T, R: RngCore> ImplicitTreap<T, R> {
+    /// Takes a ready rng, e.g. one seeded elsewhere, a mock or a borrowed &mut rng
+    pub fn with_rng(rng: R) -> Self {
+        Self {
+            root: None,
+            rng,
+            ops: Ops::default(),
+        }
+    }
+
+    /// Same as split, but the returned treap draws its priorities from rng,
+    /// so treaps whose rng can not be seeded can be split too
+    pub fn split_with<S: RngCore>(&mut self, index: usize, rng: S) -> ImplicitTreap<T, S> {
+        let len = node_size(&self.root);
+        assert!(index <= len, "`at` split index (is {index}) should be <= len (is {len})");
+        let (less, greater) = split_implicit_node(self.root.take(), index, self.ops);
+        self.root = less;
+        ImplicitTreap::from_root(greater, self.ops, rng)
+    }
+
+    /// Same as extract_range, but the returned treap draws its priorities from rng
+    pub fn extract_range_with<S: RngCore>(&mut self, left: usize, right: usize, rng: S) -> ImplicitTreap<T, S> {
+        assert!(left <= right, "range {left}..{right} is reversed");
+        assert!(right <= self.len(), "range end {right} is out of bounds for length {}", self.len());
+        let (less, rest) = split_implicit_node(self.root.take(), left, self.ops);
+        let (middle, greater) = split_implicit_node(rest, right - left, self.ops);
+        self.root = merge_implicit_nodes(less, greater, self.ops);
+        ImplicitTreap::from_root(middle, self.ops, rng)
+    }
+
+    /// Replaces the content with values in O(n)
+    pub fn init_with(&mut self, values: &[T]) 
+    where
+        T: Clone,
+    {
+        // right spine of the cartesian tree built so far
+        let mut stack: Vec<Box<ImplicitNode<T>>> = Vec::new();
+
+        for value in values {
+            let mut node = Box::new(ImplicitNode::new(value.clone(), self.rng.next_u64()));
+            let mut last = None;
+
+            while stack.last().is_some_and(|top| top.priority < node.priority) {
+                let mut top = stack.pop().unwrap();
+                top.right = last;
+                top.update(self.ops);
+                last = Some(top);
+            }
+
+            node.left = last;
+            stack.push(node);
+        }
+
+        self.root = stack.into_iter().rev().fold(None, |last, mut top| {
+            top.right = last;
+            top.update(self.ops);
+            Some(top)
+        });
+    }
+
+    /// We can not ensure this requirments with Rust
+    /// (1) merge(merge(a, b), c) = merge(a, merge(b, c))
+    pub fn with_merge(mut self, merge_fn: fn(&T, &T) -> T) -> Self {
+        self.ops.merge_fn = Some(merge_fn);
+        rebuild_implicit_node(&mut self.root, self.ops);
+        self
+    }
+
+    /// lazy_fn(aggregate, delta, len) adds delta to each of len values
+    /// folded into aggregate, e.g. aggregate + delta * len for sums
+    ///
+    /// We can not ensure this requirments with Rust
+    /// (1) lazy(lazy(a, b, k), c, k) = lazy(a, lazy(b, c, 1), k)
+    /// (2) lazy(merge(a, b), c, k + m) = merge(lazy(a, c, k), lazy(b, c, m))
+    pub fn with_lazy(mut self, lazy_fn: fn(&T, &T, usize) -> T) -> Self 
+    where
+        T: Clone,
+    {
+        self.ops.lazy_fn = Some(lazy_fn);
+        self.ops.clone_fn = Some(T::clone);
+        self
+    }
+   
+    /// Applies pending adds of the ancestors to a copy of the value kept in the node,
+    /// later reads return the copy until the next add reaches the value
+    pub fn get(&self, index: usize) -> Option<&T> {
+        let mut node = self.root.as_deref();
+        let mut index = index;
+        let mut reversed = false;
+        let mut pending = None;
+
+        while let Some(nd) = node {
+            nd.mark_cached(self.ops);
+            reversed ^= nd.reversed;
+            let (left, right) = if reversed {
+                (&nd.right, &nd.left)
+            } else {
+                (&nd.left, &nd.right)
+            };
+            let left_size = node_size(left);
+
+            if left_size == index {
+                return Some(nd.resolved_value(pending, self.ops));
+            }
+
+            pending = nd.delayed_below(&pending, self.ops);
+            if left_size < index {
+                node = right.as_deref();
+                index -= left_size + 1;
+            } else {
+                node = left.as_deref();
+            }
+        }
+
+        None
+    }
+
+    /// Same as get, but tells the length when index is out of bounds
+    pub fn try_get(&self, index: usize) -> Result<&T, IndexError> {
+        self.get(index).ok_or(IndexError { index, len: self.len() })
+    }
+
+    /// Marks the path to the value as stale, aggregates of its ancestors
+    /// are recomputed by the next operation that splits or merges them
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        let mut node = self.root.as_mut();
+        let mut index = index;
+
+        while let Some(nd) = node {
+            nd.push(self.ops);
+            nd.stale |= self.ops.merge_fn.is_some();
+            let left_size = node_size(&nd.left);
+            
+            if left_size == index {
+                nd.resolved.take();
+                return Some(&mut nd.value);
+            }
+                
+            if left_size < index {
+                node = nd.right.as_mut();
+                index -= left_size + 1;
+            } else {
+                node = nd.left.as_mut();
+            }
+        }
+        
+        None
+    }
+
+    /// Moves the values of other after the values of self, leaving other empty,
+    /// keeps the rng of self
+    pub fn append(&mut self, other: &mut ImplicitTreap<T, R>) {
+        self.root = merge_implicit_nodes(self.root.take(), other.root.take(), self.ops);
+    }
+
+    /// index = len appends, panics if index > len,
+    /// use try_insert_before to get a Result instead
+    //TODO: Better approach 
+    pub fn insert_before(&mut self, index: usize, value: T) { 
+        let len = self.len();
+        assert!(index <= len, "insertion index (is {index}) should be <= len (is {len})");
+
+        let (less, mut greater) = split_implicit_node(self.root.take(), index, self.ops);
+        let new_node = ImplicitNode::new(value, self.rng.next_u64());
+        greater = merge_implicit_nodes(new_node.into_root(), greater, self.ops);
+        self.root = merge_implicit_nodes(less, greater, self.ops); 
+    }
+
+    /// Same as insert_before, but rejects index > len
+    pub fn try_insert_before(&mut self, index: usize, value: T) -> Result<(), IndexError> {
+        let len = self.len();
+        if index > len {
+            return Err(IndexError { index, len });
+        }
+
+        self.insert_before(index, value);
+        Ok(())
+    }
+    
+    /// left inclusive
+    /// right exclusive
+    pub fn remove_range(&mut self, left: usize, right: usize) {
+        assert!(left <= right, "range {left}..{right} is reversed");
+        assert!(right <= self.len(), "range end {right} is out of bounds for length {}", self.len());
+        let (less, rest) = split_implicit_node(self.root.take(), left, self.ops);
+        let (_, greater) = split_implicit_node(rest, right - left, self.ops);
+        self.root = merge_implicit_nodes(less, greater, self.ops);
+    }
+
+    /// left inclusive
+    /// right exclusive
+    /// Same as remove_range, but yields the removed values in positional order
+    pub fn drain(&mut self, left: usize, right: usize) -> impl Iterator<Item = T> {
+        assert!(left <= right, "range {left}..{right} is reversed");
+        assert!(right <= self.len(), "range end {right} is out of bounds for length {}", self.len());
+        let (less, greater) = split_implicit_node(self.root.take(), left, self.ops);
+        let (middle, greater) = split_implicit_node(greater, right - left, self.ops);
+        self.root = merge_implicit_nodes(less, greater, self.ops);
+
+        let mut values = Vec::with_capacity(node_size(&middle));
+        take_implicit_values(middle, self.ops, &mut values);
+        values.into_iter()
+    }
+
+    /// left inclusive
+    /// right exclusive
+    /// Aggregates are not reversed, so merge should be commutative
+    pub fn reverse_range(&mut self, left: usize, right: usize) {
+        assert!(left <= right, "range {left}..{right} is reversed");
+        assert!(right <= self.len(), "range end {right} is out of bounds for length {}", self.len());
+        let (less, greater) = split_implicit_node(self.root.take(), left, self.ops);
+        let (mut middle, greater) = split_implicit_node(greater, right - left, self.ops);
+        
+        if let Some(middle) = middle.as_mut() {
+            middle.reversed ^= true;
+        }
+
+        let greater = merge_implicit_nodes(middle, greater, self.ops);
+        self.root = merge_implicit_nodes(less, greater, self.ops);
+    }
+
+    /// Cyclic shift, the value at k becomes the first, k is taken modulo len
+    pub fn rotate_left(&mut self, k: usize) {
+        let len = node_size(&self.root);
+        if len == 0 {
+            return;
+        }
+
+        let (less, greater) = split_implicit_node(self.root.take(), k % len, self.ops);
+        self.root = merge_implicit_nodes(greater, less, self.ops);
+    }
+
+    /// Cyclic shift, the value at len - k becomes the first, k is taken modulo len
+    pub fn rotate_right(&mut self, k: usize) {
+        let len = node_size(&self.root);
+        if len == 0 {
+            return;
+        }
+
+        self.rotate_left(len - k % len);
+    }
+
+    /// left inclusive
+    /// right exclusive
+    pub fn add_range(&mut self, left: usize, right: usize, delta: T) {
+        assert!(self.ops.lazy_fn.is_some(), "add_range requires a treap built with_lazy");
+        assert!(left <= right, "range {left}..{right} is reversed");
+        assert!(right <= self.len(), "range end {right} is out of bounds for length {}", self.len());
+
+        let (less, greater) = split_implicit_node(self.root.take(), left, self.ops);
+        let (mut middle, greater) = split_implicit_node(greater, right - left, self.ops);
+        
+        if let Some(middle) = middle.as_mut() {
+            middle.apply(&delta, self.ops);
+            middle.forget_resolved();
+        }
+
+        let greater = merge_implicit_nodes(middle, greater, self.ops);
+        self.root = merge_implicit_nodes(less, greater, self.ops);
+    }
+
+    /// Folds values in [left, right) with the merge function
+    pub fn query_range(&mut self, left: usize, right: usize) -> T 
+    where
+        T: Clone,
+    {
+        assert!(self.ops.merge_fn.is_some(), "query_range requires a treap built with_merge");
+        assert!(left < right, "range {left}..{right} is empty");
+        assert!(right <= self.len(), "range end {right} is out of bounds for length {}", self.len());
+
+        let (less, greater) = split_implicit_node(self.root.take(), left, self.ops);
+        let (middle, greater) = split_implicit_node(greater, right - left, self.ops);
+        let res = middle.as_ref()
+            .unwrap()
+            .aggregate()
+            .clone();
+
+        let greater = merge_implicit_nodes(middle, greater, self.ops);
+        self.root = merge_implicit_nodes(less, greater, self.ops);
+        res
+    }
+
+    /// Applies pending adds to copies of the values kept in the nodes, like get
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter::new(&self.root, self.ops)
+    }
+
+    pub fn to_vec(&self) -> Vec<T> 
+    where
+        T: Clone,
+    {
+        self.iter().cloned().collect()
+    }
+
+    /// Pushes pending adds and reversals down on the way, marks every
+    /// visited node as stale like get_mut, so aggregates stay correct
+    ///
+    /// ```compile_fail
+    /// let mut treap = common_ds::treap::ImplicitTreap::build(&[1, 2]);
+    /// let first = treap.iter_mut();
+    /// let second = treap.iter_mut();
+    /// drop(first);
+    /// ```
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut::new(self.root.as_deref_mut(), self.ops)
+    }
+
+    /// Pushes every pending add down to the values,
+    /// which drops the copies kept for reads through &self
+    pub fn flush(&mut self) {
+        let mut stack: Vec<&mut ImplicitNode<T>> = self.root.as_deref_mut().into_iter().collect();
+
+        while let Some(node) = stack.pop() {
+            node.push(self.ops);
+            node.resolved.take();
+            *node.cached.get_mut() = false;
+            stack.extend(node.left.as_deref_mut());
+            stack.extend(node.right.as_deref_mut());
+        }
+    }
+
+    /// Drops every value, keeps the rng and the functions
+    pub fn clear(&mut self) {
+        self.root = None;
+    }
+
+    /// Keeps the values with f(value) in their order in O(n),
+    /// kept nodes are relinked with their priorities, like in init_with
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut f: F) {
+        let mut pending = Vec::new();
+        // right spine of the cartesian tree built so far
+        let mut stack: Vec<Box<ImplicitNode<T>>> = Vec::new();
+        let mut next = self.root.take();
+
+        loop {
+            while let Some(mut nd) = next {
+                nd.push(self.ops);
+                next = nd.left.take();
+                pending.push(nd);
+            }
+
+            let Some(mut node) = pending.pop() else {
+                break;
+            };
+
+            next = node.right.take();
+            if !f(&node.value) {
+                continue;
+            }
+
+            let mut last = None;
+            while stack.last().is_some_and(|top| top.priority < node.priority) {
+                let mut top = stack.pop().unwrap();
+                top.right = last;
+                top.update(self.ops);
+                last = Some(top);
+            }
+
+            node.left = last;
+            stack.push(node);
+        }
+
+        self.root = stack.into_iter().rev().fold(None, |last, mut top| {
+            top.right = last;
+            top.update(self.ops);
+            Some(top)
+        });
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        node_size(&self.root)
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Checks heap order of priorities and sizes of every node
+    pub fn check_invariants(&self) -> Result<(), String> {
+        check_implicit_nodes(&self.root).map_err(|err| err.to_string())
+    }
+
+    /// Same as check_invariants
+    #[cfg(any(test, feature = "validate"))]
+    pub fn validate(&self) -> Result<(), InvariantViolation> {
+        check_implicit_nodes(&self.root)
+    }
+
+    /// Nodes on the longest path from the root, 0 for no elements
+    pub fn height(&self) -> usize {
+        let mut res = 0;
+        for_each_implicit_depth(&self.root, |depth| res = res.max(depth));
+        res
+    }
+
+    /// Mean number of nodes on the path from the root to a node, 0 for no elements
+    pub fn average_depth(&self) -> f64 {
+        let mut total = 0;
+        for_each_implicit_depth(&self.root, |depth| total += depth);
+
+        match self.len() {
+            0 => 0.0,
+            len => total as f64 / len as f64,
+        }
+    }
+
+    /// Returns None if index is out of range
+    pub fn remove(&mut self, index: usize) -> Option<T> {
+        let (less, greater) = split_implicit_node(self.root.take(), index, self.ops);
+        let (middle, greater) = split_implicit_node(greater, 1, self.ops);
+        self.root = merge_implicit_nodes(less, greater, self.ops);
+        middle.map(|node| node.value)
+    }
+
+    fn from_root(root: ImplicitLink<T>, ops: Ops<T>, rng: R) -> Self {
+        Self {
+            root,
+            rng,
+            ops,
+        }
+    }
+}
+
+/// Same as get, but panics if index is out of bounds
+impl<T, R: RngCore> Index<usize> for ImplicitTreap<T, R> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        match self.get(index) {
+            Some(value) => value,
+            None => panic!("index out of bounds: the len is {} but the index is {index}", node_size(&self.root)),
+        }
+    }
+}
+
+/// The copy gets an rng forked from a copy of the rng of self,
+/// so both treaps do not draw the same priorities afterwards
+impl<T: Clone, R: Clone + SeedableRng + RngCore> Clone for ImplicitTreap<T, R> {
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+            rng: fork_rng(&mut self.rng.clone()),
+            ops: self.ops,
+        }
+    }
+}
+
+/// Same values in the same order, whatever the shapes, O(n)
+/// Pending adds are applied to copies of the values
+impl<T: PartialEq, R: RngCore> PartialEq for ImplicitTreap<T, R> {
+    fn eq(&self, other: &Self) -> bool {
+        node_size(&self.root) == node_size(&other.root) && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq, R: RngCore> Eq for ImplicitTreap<T, R> {}
+
+/// Values in order like a list, {:#?} also shows the size and priority of every node,
+/// pending adds are applied to copies of the values
+impl<T: fmt::Debug, R> fmt::Debug for ImplicitTreap<T, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let alternate = f.alternate();
+        let mut list = f.debug_list();
+
+        for (node, value) in ResolvedNodes::new(&self.root, self.ops) {
+            if alternate {
+                list.entry(&DebugNode { name: "value", value, size: node.size, priority: node.priority });
+            } else {
+                list.entry(value);
+            }
+        }
+
+        list.finish()
+    }
+}
+
+/// Pending adds are applied to copies of the values
+impl<'a, T: PartialEq, R: RngCore> PartialEq<&'a [T]> for ImplicitTreap<T, R> {
+    fn eq(&self, other: &&'a [T]) -> bool {
+        node_size(&self.root) == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: PartialEq, R: RngCore> PartialEq<Vec<T>> for ImplicitTreap<T, R> {
+    fn eq(&self, other: &Vec<T>) -> bool {
+        *self == other.as_slice()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T, R: SeedableRng + RngCore> FromIterator<T> for ImplicitTreap<T, R> {
+    fn from_iter<I: IntoIterator<Item = T>>(values: I) -> Self {
+        let mut res = Self::default();
+        res.extend(values);
+        res
+    }
+}
+
+/// Appends values after the last one
+impl<T, R: RngCore> Extend<T> for ImplicitTreap<T, R> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, values: I) {
+        for value in values {
+            let new_node = ImplicitNode::new(value, self.rng.next_u64());
+            self.root = merge_implicit_nodes(self.root.take(), new_node.into_root(), self.ops);
+        }
+    }
+}
+
+/// Pushes pending adds and reversals down on the way, needs no T: Clone
+impl<T, R: RngCore> IntoIterator for ImplicitTreap<T, R> {
+    type Item = T;
+    type IntoIter = ImplicitIntoIter<T>;
+
+    fn into_iter(mut self) -> ImplicitIntoIter<T> {
+        ImplicitIntoIter::new(self.root.take(), self.ops)
+    }
+}
+
+impl<'a, T, R: RngCore> IntoIterator for &'a ImplicitTreap<T, R> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<'a, T, R: RngCore> IntoIterator for &'a mut ImplicitTreap<T, R> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> IterMut<'a, T> {
+        self.iter_mut()
+    }
+}
+
+/// Same as get_mut, but panics if index is out of bounds
+impl<T, R: RngCore> IndexMut<usize> for ImplicitTreap<T, R> {
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        let len = node_size(&self.root);
+
+        match self.get_mut(index) {
+            Some(value) => value,
+            None => panic!("index out of bounds: the len is {len} but the index is {index}"),
+        }
+    }
+}
+
+fn after_start<K: Borrow<Q>, Q: Ord + ?Sized>(key: &K, start: Bound<&Q>) -> bool {
+    match start {
+        Bound::Included(start) => key.borrow() >= start,
+        Bound::Excluded(start) => key.borrow() > start,
+        Bound::Unbounded => true,
+    }
+}
+
+fn before_end<K: Borrow<Q>, Q: Ord + ?Sized>(key: &K, end: Bound<&Q>) -> bool {
+    match end {
+        Bound::Included(end) => key.borrow() <= end,
+        Bound::Excluded(end) => key.borrow() < end,
+        Bound::Unbounded => true,
+    }
+}
+
+/// Seeds the rng of a split off half from the rng of the other half,
+/// so that seeded treaps stay reproducible without entropy
+fn fork_rng<R: SeedableRng, S: RngCore>(rng: &mut S) -> R {
+    R::seed_from_u64(rng.next_u64())
+}
+
+/// Without recursion, the nodes on the merge path are kept on a stack
+/// with whether the rest of the merge goes to their right child
+fn merge_nodes<K, V>(mut left: Link<K, V>, mut right: Link<K, V>) 
+-> Link<K, V> {
+    let mut stack = Vec::new();
+
+    let mut res = loop {
+        match (left, right) {
+            (None, rest) | (rest, None) => break rest,
+            (Some(mut l), Some(mut r)) => {
+                if l.priority > r.priority {
+                    left = l.right.take();
+                    right = Some(r);
+                    stack.push((l, true));
+                } else {
+                    right = r.left.take();
+                    left = Some(l);
+                    stack.push((r, false));
+                }
+            }
+        }
+    };
+
+    while let Some((mut node, to_right)) = stack.pop() {
+        if to_right {
+            node.right = res;
+        } else {
+            node.left = res;
+        }
+
+        node.update_size();
+        res = Some(node);
+    }
+
+    res
+}
+
+fn split_node<K: Ord + Borrow<Q>, V, Q: Ord + ?Sized>(node: Link<K, V>, key: &Q) 
+-> (Link<K, V>, Link<K, V>) {
+    split_node_by(node, &|node_key: &K| node_key.borrow() < key)
+}
+
+/// Keys with goes_left go to the first half, goes_left must be true
+/// for a prefix of the keys in order
+fn split_node_by<K, V, F: Fn(&K) -> bool>(mut node: Link<K, V>, goes_left: &F) 
+-> (Link<K, V>, Link<K, V>) {
+    let mut stack = Vec::new();
+    while let Some(mut nd) = node {
+        let left = goes_left(&nd.key);
+        node = if left { nd.right.take() } else { nd.left.take() };
+        stack.push((nd, left));
+    }
+
+    let (mut less, mut greater) = (None, None);
+    while let Some((mut node, left)) = stack.pop() {
+        if left {
+            node.right = less;
+            node.update_size();
+            less = Some(node);
+        } else {
+            node.left = greater;
+            node.update_size();
+            greater = Some(node);
+        }
+    }
+
+    (less, greater)
+}
+
+/// First count keys go to the first half, like split_implicit_node
+fn split_node_at<K, V>(mut node: Link<K, V>, mut count: usize) 
+-> (Link<K, V>, Link<K, V>) {
+    let mut stack = Vec::new();
+    while let Some(mut nd) = node {
+        let left_size = key_node_size(&nd.left);
+        let left = left_size < count;
+        node = if left {
+            count -= left_size + 1;
+            nd.right.take()
+        } else {
+            nd.left.take()
+        };
+        stack.push((nd, left));
+    }
+
+    let (mut less, mut greater) = (None, None);
+    while let Some((mut node, left)) = stack.pop() {
+        if left {
+            node.right = less;
+            node.update_size();
+            less = Some(node);
+        } else {
+            node.left = greater;
+            node.update_size();
+            greater = Some(node);
+        }
+    }
+
+    (less, greater)
+}
+
+/// Descends while priorities are greater, then splits the rest by key,
+/// returns the value under key and whether it was inserted
+fn insert_node<K: Ord, V, F: FnOnce() -> V>(link: &mut Link<K, V>, key: K, priority: u64, value: F) 
+-> (&mut V, bool) {
+    if link.as_ref().is_some_and(|node| node.priority >= priority) {
+        let node = link.as_mut().unwrap();
+        let res = match key.cmp(&node.key) {
+            Ordering::Less => insert_node(&mut node.left, key, priority, value),
+            Ordering::Greater => insert_node(&mut node.right, key, priority, value),
+            Ordering::Equal => return (&mut node.value, false),
+        };
+
+        if res.1 {
+            node.size += 1;
+        }
+
+        return res;
+    }
+
+    let (less, equal, greater) = split_out(link.take(), &key);
+    if equal.is_some() {
+        *link = merge_nodes(merge_nodes(less, equal), greater);
+        let node = find_node_mut(link, &key).unwrap();
+        return (&mut node.value, false);
+    }
+
+    let mut node = Node::new(key, value(), priority);
+    node.left = less;
+    node.right = greater;
+    node.update_size();
+
+    let node = link.insert(Box::new(node));
+    (&mut node.value, true)
+}
+
+fn first_node<K, V>(link: &Link<K, V>) -> Option<&Node<K, V>> {
+    let mut node = link.as_deref()?;
+    while let Some(left) = node.left.as_deref() {
+        node = left;
+    }
+
+    Some(node)
+}
+
+fn last_node<K, V>(link: &Link<K, V>) -> Option<&Node<K, V>> {
+    let mut node = link.as_deref()?;
+    while let Some(right) = node.right.as_deref() {
+        node = right;
+    }
+
+    Some(node)
+}
+
+fn find_node<'a, K: Ord + Borrow<Q>, V, Q: Ord + ?Sized>(link: &'a Link<K, V>, key: &Q) 
+-> Option<&'a Node<K, V>> {
+    let mut node = link.as_deref();
+    while let Some(nd) = node {
+        node = match key.cmp(nd.key.borrow()) {
+            Ordering::Less => nd.left.as_deref(),
+            Ordering::Greater => nd.right.as_deref(),
+            Ordering::Equal => return Some(nd),
+        };
+    }
+
+    None
+}
+
+fn find_node_mut<'a, K: Ord + Borrow<Q>, V, Q: Ord + ?Sized>(link: &'a mut Link<K, V>, key: &Q) 
+-> Option<&'a mut Node<K, V>> {
+    let mut node = link.as_deref_mut();
+    while let Some(nd) = node {
+        node = match key.cmp(nd.key.borrow()) {
+            Ordering::Less => nd.left.as_deref_mut(),
+            Ordering::Greater => nd.right.as_deref_mut(),
+            Ordering::Equal => return Some(nd),
+        };
+    }
+
+    None
+}
+
+/// Splits node into keys less than key, the node with key if any, and keys greater than key,
+/// in a single descent, which stops at the node with key
+fn split_out<K: Ord + Borrow<Q>, V, Q: Ord + ?Sized>(mut node: Link<K, V>, key: &Q) 
+-> (Link<K, V>, Link<K, V>, Link<K, V>) {
+    let mut stack = Vec::new();
+    let (mut less, mut equal, mut greater) = (None, None, None);
+
+    while let Some(mut nd) = node {
+        let ordering = nd.key.borrow().cmp(key);
+        node = match ordering {
+            Ordering::Less => nd.right.take(),
+            Ordering::Greater => nd.left.take(),
+            Ordering::Equal => {
+                less = nd.left.take();
+                greater = nd.right.take();
+                nd.update_size();
+                equal = Some(nd);
+                break;
+            }
+        };
+
+        stack.push((nd, ordering == Ordering::Less));
+    }
+
+    while let Some((mut node, left)) = stack.pop() {
+        if left {
+            node.right = less;
+            node.update_size();
+            less = Some(node);
+        } else {
+            node.left = greater;
+            node.update_size();
+            greater = Some(node);
+        }
+    }
+
+    (less, equal, greater)
+}
+
+/// The root with the greater priority splits the other one
+fn union_nodes<K: Ord>(left: Link<K>, right: Link<K>) -> Link<K> {
+    let (mut first, second) = match (left, right) {
+        (None, other) | (other, None) => return other,
+        (Some(left), Some(right)) if left.priority >= right.priority => (left, right),
+        (Some(left), Some(right)) => (right, left),
+    };
+
+    let (less, _, greater) = split_out(Some(second), &first.key);
+    first.left = union_nodes(first.left.take(), less);
+    first.right = union_nodes(first.right.take(), greater);
+    first.update_size();
+    Some(first)
+}
+
+fn intersect_nodes<K: Ord>(left: Link<K>, right: Link<K>) -> Link<K> {
+    let (mut first, second) = match (left, right) {
+        (None, _) | (_, None) => return None,
+        (Some(left), Some(right)) if left.priority >= right.priority => (left, right),
+        (Some(left), Some(right)) => (right, left),
+    };
+
+    let (less, equal, greater) = split_out(Some(second), &first.key);
+    let left = intersect_nodes(first.left.take(), less);
+    let right = intersect_nodes(first.right.take(), greater);
+
+    if equal.is_none() {
+        return merge_nodes(left, right);
+    }
+
+    first.left = left;
+    first.right = right;
+    first.update_size();
+    Some(first)
+}
+
+/// Keys of node not in other
+fn difference_nodes<K: Ord>(node: Link<K>, other: Link<K>) -> Link<K> {
+    let mut node = match (node, other.is_some()) {
+        (Some(node), true) => node,
+        (node, _) => return node,
+    };
+
+    let (less, equal, greater) = split_out(other, &node.key);
+    let left = difference_nodes(node.left.take(), less);
+    let right = difference_nodes(node.right.take(), greater);
+
+    if equal.is_some() {
+        return merge_nodes(left, right);
+    }
+
+    node.left = left;
+    node.right = right;
+    node.update_size();
+    Some(node)
+}
+
+/// Returns the removed key with its value
+fn remove_node<K: Ord + Borrow<Q>, V, Q: Ord + ?Sized>(link: &mut Link<K, V>, key: &Q) -> Option<(K, V)> {
+    let node = link.as_mut()?;
+
+    let removed = match key.cmp(node.key.borrow()) {
+        Ordering::Less => remove_node(&mut node.left, key),
+        Ordering::Greater => remove_node(&mut node.right, key),
+        Ordering::Equal => {
+            let node = link.take()?;
+            *link = merge_nodes(node.left, node.right);
+            return Some((node.key, node.value));
+        }
+    };
+
+    if removed.is_some() {
+        node.size -= 1;
+    }
+
+    removed
+}
+
+/// Replaces the leftmost node by its right child
+fn pop_first_node<K, V>(link: &mut Link<K, V>) -> Link<K, V> {
+    let node = link.as_mut()?;
+
+    if node.left.is_some() {
+        let res = pop_first_node(&mut node.left);
+        node.size -= 1;
+        return res;
+    }
+
+    let mut node = link.take()?;
+    *link = node.right.take();
+    Some(node)
+}
+
+/// Replaces the rightmost node by its left child
+fn pop_last_node<K, V>(link: &mut Link<K, V>) -> Link<K, V> {
+    let node = link.as_mut()?;
+
+    if node.right.is_some() {
+        let res = pop_last_node(&mut node.right);
+        node.size -= 1;
+        return res;
+    }
+
+    let mut node = link.take()?;
+    *link = node.left.take();
+    Some(node)
+}
+
+/// Key order, heap order of priorities and sizes, without recursion
+fn check_key_nodes<K: Ord, V>(root: &Link<K, V>) -> Result<(), InvariantViolation> {
+    let mut stack: Vec<_> = root.as_deref().map(|root| (root, None, None)).into_iter().collect();
+
+    while let Some((node, low, high)) = stack.pop() {
+        if low.is_some_and(|low: &K| *low >= node.key) || high.is_some_and(|high: &K| node.key >= *high) {
+            return Err(InvariantViolation::KeysOutOfOrder);
+        }
+
+        let expected = key_node_size(&node.left) + key_node_size(&node.right) + 1;
+        if node.size != expected {
+            return Err(InvariantViolation::Size { size: node.size, expected });
+        }
+
+        for child in [node.left.as_deref(), node.right.as_deref()].into_iter().flatten() {
+            if child.priority > node.priority {
+                return Err(InvariantViolation::PriorityOrder { parent: node.priority, child: child.priority });
+            }
+        }
+
+        stack.extend(node.left.as_deref().map(|left| (left, low, Some(&node.key))));
+        stack.extend(node.right.as_deref().map(|right| (right, Some(&node.key), high)));
+    }
+
+    Ok(())
+}
+
+/// Heap order of priorities and sizes, without recursion
+fn check_implicit_nodes<T>(root: &ImplicitLink<T>) -> Result<(), InvariantViolation> {
+    let mut stack: Vec<&ImplicitNode<T>> = root.as_deref().into_iter().collect();
+
+    while let Some(node) = stack.pop() {
+        let expected = node_size(&node.left) + node_size(&node.right) + 1;
+        if node.size != expected {
+            return Err(InvariantViolation::Size { size: node.size, expected });
+        }
+
+        for child in [node.left.as_deref(), node.right.as_deref()].into_iter().flatten() {
+            if child.priority > node.priority {
+                return Err(InvariantViolation::PriorityOrder { parent: node.priority, child: child.priority });
+            }
+            stack.push(child);
+        }
+    }
+
+    Ok(())
+}
+
+/// In order walk with an explicit stack
+fn for_each_key_node<K, V, F: FnMut(&Node<K, V>)>(root: &Link<K, V>, mut f: F) {
+    let mut stack = Vec::new();
+    let mut node = root.as_deref();
+
+    loop {
+        while let Some(nd) = node {
+            stack.push(nd);
+            node = nd.left.as_deref();
+        }
+
+        let Some(nd) = stack.pop() else {
+            return;
+        };
+
+        f(nd);
+        node = nd.right.as_deref();
+    }
+}
+
+/// Calls f with the depth of every node, the root has depth 1
+fn for_each_implicit_depth<T, F: FnMut(usize)>(root: &ImplicitLink<T>, mut f: F) {
+    let mut stack: Vec<(&ImplicitNode<T>, usize)> = root.as_deref().map(|root| (root, 1)).into_iter().collect();
+    while let Some((node, depth)) = stack.pop() {
+        f(depth);
+        for child in [&node.left, &node.right].into_iter().flatten() {
+            stack.push((child, depth + 1));
+        }
+    }
+}
+
+fn key_node_size<K, V>(node: &Link<K, V>) -> usize {
+    match node {
+        None => 0,
+        Some(node) => node.size,
+    }
+}
+
+fn node_size<T>(node: &ImplicitLink<T>) -> usize {
+    match node {
+        None => 0,
+        Some(node) => node.size,
+    }
+}
+
+/// Same as merge_nodes, pushes every node on the path first
+fn merge_implicit_nodes<T>(mut left: ImplicitLink<T>, mut right: ImplicitLink<T>, ops: Ops<T>) 
+-> ImplicitLink<T> {
+    let mut stack = Vec::new();
+
+    let mut res = loop {
+        match (left, right) {
+            (None, rest) | (rest, None) => break rest,
+            (Some(mut l), Some(mut r)) => {
+                if l.priority > r.priority {
+                    l.push(ops);
+                    left = l.right.take();
+                    right = Some(r);
+                    stack.push((l, true));
+                } else {
+                    r.push(ops);
+                    right = r.left.take();
+                    left = Some(l);
+                    stack.push((r, false));
+                }
+            }
+        }
+    };
+
+    while let Some((mut node, to_right)) = stack.pop() {
+        if to_right {
+            node.right = res;
+        } else {
+            node.left = res;
+        }
+
+        node.update(ops);
+        res = Some(node);
+    }
+
+    res
+}
+
+fn split_implicit_node<T>(mut node: ImplicitLink<T>, mut index: usize, ops: Ops<T>) 
+-> (ImplicitLink<T>, ImplicitLink<T>) {
+    let mut stack = Vec::new();
+    while let Some(mut nd) = node {
+        nd.push(ops);
+        let left_size = node_size(&nd.left);
+        let left = left_size < index;
+        node = if left {
+            index -= left_size + 1;
+            nd.right.take()
+        } else {
+            nd.left.take()
+        };
+        stack.push((nd, left));
+    }
+
+    let (mut less, mut greater) = (None, None);
+    while let Some((mut node, left)) = stack.pop() {
+        if left {
+            node.right = less;
+            node.update(ops);
+            less = Some(node);
+        } else {
+            node.left = greater;
+            node.update(ops);
+            greater = Some(node);
+        }
+    }
+
+    (less, greater)
+}
+
+/// Moves the values out in positional order, without recursion
+fn take_implicit_values<T>(root: ImplicitLink<T>, ops: Ops<T>, values: &mut Vec<T>) {
+    let mut stack = Vec::new();
+    let mut node = root;
+
+    loop {
+        while let Some(mut nd) = node {
+            nd.push(ops);
+            node = nd.left.take();
+            stack.push(nd);
+        }
+
+        let Some(mut nd) = stack.pop() else {
+            break;
+        };
+
+        node = nd.right.take();
+        values.push(nd.value);
+    }
+}
+
+/// Updates every node after its children, without recursion
+fn rebuild_implicit_node<T>(node: &mut ImplicitLink<T>, ops: Ops<T>) {
+    // a node is detached from its children until they are rebuilt
+    let mut stack: Vec<_> = node.take().map(|nd| (nd, None)).into_iter().collect();
+    let mut rebuilt: Vec<Box<ImplicitNode<T>>> = Vec::new();
+
+    while let Some((mut nd, children)) = stack.pop() {
+        match children {
+            None => {
+                let (left, right) = (nd.left.take(), nd.right.take());
+                stack.push((nd, Some((left.is_some(), right.is_some()))));
+                stack.extend(right.map(|right| (right, None)));
+                stack.extend(left.map(|left| (left, None)));
+            }
+            Some((has_left, has_right)) => {
+                if has_right {
+                    nd.right = rebuilt.pop();
+                }
+                if has_left {
+                    nd.left = rebuilt.pop();
+                }
+
+                nd.update(ops);
+                rebuilt.push(nd);
+            }
+        }
+    }
+
+    *node = rebuilt.pop();
+}
+
+/// Updates every stale node after its stale children, without recursion,
+/// stale nodes are always on paths from the root, so the rest is left as is
+fn refresh_implicit_node<T>(node: &mut ImplicitLink<T>, ops: Ops<T>) {
+    if !node.as_ref().is_some_and(|nd| nd.stale) {
+        return;
+    }
+
+    // a node is detached from its stale children until they are refreshed
+    let mut stack: Vec<_> = node.take().map(|nd| (nd, None)).into_iter().collect();
+    let mut refreshed: Vec<Box<ImplicitNode<T>>> = Vec::new();
+
+    while let Some((mut nd, children)) = stack.pop() {
+        match children {
+            None => {
+                nd.push(ops);
+                let left = nd.left.take_if(|left| left.stale);
+                let right = nd.right.take_if(|right| right.stale);
+                stack.push((nd, Some((left.is_some(), right.is_some()))));
+                stack.extend(right.map(|right| (right, None)));
+                stack.extend(left.map(|left| (left, None)));
+            }
+            Some((has_left, has_right)) => {
+                if has_right {
+                    nd.right = refreshed.pop();
+                }
+                if has_left {
+                    nd.left = refreshed.pop();
+                }
+
+                nd.update(ops);
+                refreshed.push(nd);
+            }
+        }
+    }
+
+    *node = refreshed.pop();
+}
+
+impl<T> ImplicitNode<T> {
+    fn new(value: T, priority: u64) -> Self {
+        Self {
+            value,
+            aggregate: None,
+            size: 1,
+            priority,
+            reversed: false,
+            delayed: None,
+            stale: false,
+            resolved: OnceCell::new(),
+            cached: Cell::new(false),
+            left: None,
+            right: None,
+        }
+    }
+    
+    fn update(&mut self, ops: Ops<T>) {
+        refresh_implicit_node(&mut self.left, ops);
+        refresh_implicit_node(&mut self.right, ops);
+
+        self.stale = false;
+        self.size = node_size(&self.left) + node_size(&self.right) + 1;
+        *self.cached.get_mut() = self.resolved.get().is_some()
+            || [&self.left, &self.right].into_iter().flatten().any(|child| child.cached.get());
+        self.aggregate = ops.merge_fn.and_then(|merge| {
+            match (&self.left, &self.right) {
+                (None, None) => None,
+                (Some(left), None) => Some(merge(left.aggregate(), &self.value)),
+                (None, Some(right)) => Some(merge(&self.value, right.aggregate())),
+                (Some(left), Some(right)) => {
+                    Some(merge(&merge(left.aggregate(), &self.value), right.aggregate()))
+                }
+            }
+        });
+    }
+
+    fn push(&mut self, ops: Ops<T>) {
+        if self.reversed {
+            core::mem::swap(&mut self.left, &mut self.right);
+
+            for child in [&mut self.left, &mut self.right].into_iter().flatten() {
+                child.reversed ^= true;
+            }
+
+            self.reversed = false;
+        }
+
+        if let Some(delta) = self.delayed.take() {
+            for child in [&mut self.left, &mut self.right].into_iter().flatten() {
+                child.apply(&delta, ops);
+            }
+        }
+    }
+
+    /// Adds delta to every value of the subtree
+    fn apply(&mut self, delta: &T, ops: Ops<T>) {
+        let (Some(lazy), Some(clone)) = (ops.lazy_fn, ops.clone_fn) else {
+            return;
+        };
+
+        self.value = lazy(&self.value, delta, 1);
+        self.aggregate = self.aggregate.as_ref().map(|aggregate| lazy(aggregate, delta, self.size));
+
+        if self.left.is_some() || self.right.is_some() {
+            self.delayed = Some(match self.delayed.take() {
+                Some(delayed) => lazy(&delayed, delta, 1),
+                None => clone(delta),
+            });
+        }
+    }
+
+    /// Pending add of the children, pending holds the adds of the ancestors,
+    /// which are newer than delayed
+    fn delayed_below(&self, pending: &Option<T>, ops: Ops<T>) -> Option<T> {
+        let (lazy, clone) = ops.lazy_fn.zip(ops.clone_fn)?;
+        match (&self.delayed, pending) {
+            (Some(delayed), Some(pending)) => Some(lazy(delayed, pending, 1)),
+            (Some(delta), None) | (None, Some(delta)) => Some(clone(delta)),
+            (None, None) => None,
+        }
+    }
+
+    /// Value with the pending adds of the ancestors, computed once
+    fn resolved_value(&self, pending: Option<T>, ops: Ops<T>) -> &T {
+        match (pending, ops.lazy_fn) {
+            (Some(pending), Some(lazy)) => self.resolved.get_or_init(|| lazy(&self.value, &pending, 1)),
+            _ => &self.value,
+        }
+    }
+
+    /// Reads through &self may keep a resolved value in the subtree
+    fn mark_cached(&self, ops: Ops<T>) {
+        if ops.lazy_fn.is_some() {
+            self.cached.set(true);
+        }
+    }
+
+    /// Drops the resolved values of the subtree, which miss an add applied to it,
+    /// only visits the nodes marked as cached
+    fn forget_resolved(&mut self) {
+        let mut stack = Vec::from([self]);
+
+        while let Some(node) = stack.pop() {
+            if !core::mem::take(node.cached.get_mut()) {
+                continue;
+            }
+
+            node.resolved.take();
+            stack.extend(node.left.as_deref_mut());
+            stack.extend(node.right.as_deref_mut());
+        }
+    }
+
+    #[inline]
+    fn aggregate(&self) -> &T {
+        self.aggregate.as_ref().unwrap_or(&self.value)
+    }
+
+    fn into_root(self) -> Option<Box<Self>> {
+        Some(Box::new(self))
+    }
+}
+
+impl<'a, T: fmt::Debug> fmt::Debug for DebugNode<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Node")
+            .field(self.name, self.value)
+            .field("size", &self.size)
+            .field("priority", &self.priority)
+            .finish()
+    }
+}
+
+impl<'a, T> Iter<'a, T> {
+    fn new(root: &'a ImplicitLink<T>, ops: Ops<T>) -> Self {
+        Self {
+            nodes: ResolvedNodes::new(root, ops),
+            len: node_size(root),
+        }
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let (_, value) = self.nodes.next()?;
+        self.len -= 1;
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for Iter<'a, T> {}
+
+impl<'a, T> ResolvedNodes<'a, T> {
+    fn new(root: &'a ImplicitLink<T>, ops: Ops<T>) -> Self {
+        let mut res = Self {
+            stack: Vec::new(),
+            ops,
+        };
+
+        res.push_left(root.as_deref(), false, None);
+        res
+    }
+
+    /// Pushes the leftmost path, reversed and pending are above node
+    fn push_left(&mut self, mut node: Option<&'a ImplicitNode<T>>, mut reversed: bool, mut pending: Option<T>) {
+        while let Some(nd) = node {
+            nd.mark_cached(self.ops);
+            reversed ^= nd.reversed;
+            let below = nd.delayed_below(&pending, self.ops);
+            self.stack.push((nd, reversed, pending));
+
+            node = if reversed { nd.right.as_deref() } else { nd.left.as_deref() };
+            pending = below;
+        }
+    }
+}
+
+impl<'a, T> Iterator for ResolvedNodes<'a, T> {
+    type Item = (&'a ImplicitNode<T>, &'a T);
+
+    fn next(&mut self) -> Option<(&'a ImplicitNode<T>, &'a T)> {
+        let (node, reversed, pending) = self.stack.pop()?;
+        let right = if reversed { node.left.as_deref() } else { node.right.as_deref() };
+        self.push_left(right, reversed, node.delayed_below(&pending, self.ops));
+        Some((node, node.resolved_value(pending, self.ops)))
+    }
+}
+
+impl<T> ImplicitIntoIter<T> {
+    fn new(root: ImplicitLink<T>, ops: Ops<T>) -> Self {
+        let mut res = Self {
+            stack: Vec::new(),
+            ops,
+            len: node_size(&root),
+        };
+
+        res.push_left(root);
+        res
+    }
+
+    fn push_left(&mut self, mut node: ImplicitLink<T>) {
+        while let Some(mut nd) = node {
+            nd.push(self.ops);
+            node = nd.left.take();
+            self.stack.push(nd);
+        }
+    }
+}
+
+impl<T> Iterator for ImplicitIntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let mut node = self.stack.pop()?;
+        self.push_left(node.right.take());
+        self.len -= 1;
+        Some(node.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T> ExactSizeIterator for ImplicitIntoIter<T> {}
+
+/// Drops node by node, so deep treaps can not overflow the stack
+impl<T> Drop for ImplicitIntoIter<T> {
+    fn drop(&mut self) {
+        while self.next().is_some() {}
+    }
+}
+
+impl<'a, T> IterMut<'a, T> {
+    fn new(root: Option<&'a mut ImplicitNode<T>>, ops: Ops<T>) -> Self {
+        let mut res = Self {
+            stack: Vec::new(),
+            ops,
+            len: root.as_ref().map_or(0, |root| root.size),
+        };
+
+        res.push_left(root);
+        res
+    }
+
+    /// Pushes the leftmost path, splitting every node into its value and right subtree
+    fn push_left(&mut self, mut node: Option<&'a mut ImplicitNode<T>>) {
+        while let Some(nd) = node {
+            nd.push(self.ops);
+
+            nd.stale |= self.ops.merge_fn.is_some();
+            nd.resolved.take();
+
+            let ImplicitNode { value, left, right, .. } = nd;
+            self.stack.push((value, right.as_deref_mut()));
+            node = left.as_deref_mut();
+        }
+    }
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<&'a mut T> {
+        let (value, right) = self.stack.pop()?;
+        self.push_left(right);
+        self.len -= 1;
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for IterMut<'a, T> {}
+
+impl<T> Default for Ops<T> {
+    fn default() -> Self {
+        Self {
+            merge_fn: None,
+            lazy_fn: None,
+            clone_fn: None,
+        }
+    }
+}
+
+impl<T> Clone for Ops<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Ops<T> {}
+
+impl<K, V> Node<K, V> {
+    fn new(key: K, value: V, priority: u64) -> Self {
+        Self {
+            key,
+            value,
+            size: 1,
+            priority,
+            left: None,
+            right: None,
+        }
+    }
+
+    fn update_size(&mut self) {
+        self.size = key_node_size(&self.left) + key_node_size(&self.right) + 1;
+    }
+
+    fn into_root(self) -> Option<Box<Self>> {
+        Some(Box::new(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use std::collections::BTreeSet;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    /// Checks key order, heap order of priorities and sizes
+    fn assert_treap_invariants<K: Ord, V>(root: &Link<K, V>) {
+        if let Err(err) = check_key_nodes(root) {
+            panic!("{err}");
+        }
+    }
+
+    #[test]
+    fn treap_height_is_logarithmic() {
+        const N: usize = 200_000;
+
+        let mut rng = SmallRng::seed_from_u64(1);
+        let mut treap = Treap::<u64, SmallRng>::from_seed([42; 32]);
+        while treap.len() < N {
+            treap.insert(rng.gen());
+        }
+
+        let mut height = 0;
+        let mut stack = vec![(treap.root.as_deref().unwrap(), 1)];
+        while let Some((node, depth)) = stack.pop() {
+            height = height.max(depth);
+            stack.extend([node.left.as_deref(), node.right.as_deref()].into_iter().flatten().map(|child| (child, depth + 1)));
+        }
+
+        assert!(height <= 3 * N.ilog2(), "height {height} for {N} keys");
+    }
+
+    #[test]
+    fn implicit_treap_balance() {
+        const N: usize = 10_000;
+
+        let mut rng = SmallRng::seed_from_u64(2);
+        let mut treap = ImplicitTreap::<u32, SmallRng>::from_seed([55; 32]);
+        assert_eq!(treap.height(), 0);
+        assert_eq!(treap.average_depth(), 0.0);
+
+        for len in 0..N {
+            treap.insert_before(rng.gen_range(0..=len), len as u32);
+        }
+
+        let log = N.ilog2() as usize;
+        assert!(treap.height() <= 3 * log, "height {} for {N} values", treap.height());
+        assert!(treap.average_depth() <= 2.0 * log as f64, "average depth {} for {N} values", treap.average_depth());
+        assert!(treap.average_depth() <= treap.height() as f64);
+
+        let mut chain = ImplicitTreap::with_rng(DescendingRng(u32::MAX));
+        chain.extend(0..100);
+        assert_eq!(chain.height(), 100);
+        assert_eq!(chain.average_depth(), 50.5);
+    }
+
+    #[test]
+    fn treap_init_with_sorted() {
+        let mut rng = SmallRng::seed_from_u64(3);
+        let mut keys: Vec<u32> = (0..1_000_000).map(|_| rng.gen_range(0..4_000_000)).collect();
+        keys.sort();
+
+        let mut treap = Treap::<u32, SmallRng>::from_seed([17; 32]);
+        treap.init_with_sorted(keys.iter().copied());
+        let set: BTreeSet<u32> = keys.iter().copied().collect();
+
+        assert_treap_invariants(&treap.root);
+        assert_eq!(treap.len(), set.len());
+        for _ in 0..10_000 {
+            let key = rng.gen_range(0..4_000_000);
+            assert_eq!(treap.contains(&key), set.contains(&key), "key: {key}");
+        }
+
+        treap.extend([7, 4_000_001, 3]);
+        assert_treap_invariants(&treap.root);
+        assert!(treap.contains(&4_000_001));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn treap_from_iter() {
+        let treap: Treap<i32> = [5, 1, 4, 1, 5, 9, 2, 6].into_iter().collect();
+
+        assert_treap_invariants(&treap.root);
+        assert_eq!(treap.len(), 6);
+        assert!(treap.into_iter().eq([1, 2, 4, 5, 6, 9]));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn treap_works() {
+        let mut treap = Treap::new();
+            
+        treap.insert(10);
+        assert!(treap.contains(&10));
+        assert!(!treap.contains(&5));
+        treap.insert(5);
+        assert!(treap.contains(&10));
+        assert!(treap.contains(&5));
+        treap.remove_range(&5, &6);
+        assert!(treap.contains(&10));
+        assert!(!treap.contains(&5));
+        assert_eq!(treap.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn check_invariants_detects_corruption() {
+        let mut treap = Treap::<u32, SmallRng>::from_seed([32; 32]);
+        treap.extend(0..100);
+        assert_eq!(treap.check_invariants(), Ok(()));
+
+        let mut corrupted = treap.clone();
+        corrupted.root.as_mut().unwrap().size += 1;
+        assert!(corrupted.check_invariants().is_err());
+
+        let mut corrupted = treap.clone();
+        corrupted.root.as_mut().unwrap().key = 1_000;
+        assert!(corrupted.check_invariants().is_err());
+        assert_eq!(corrupted.validate(), Err(InvariantViolation::KeysOutOfOrder));
+
+        let mut corrupted = treap.clone();
+        corrupted.root.as_mut().unwrap().priority = 0;
+        assert!(corrupted.check_invariants().is_err());
+
+        let mut implicit = ImplicitTreap::<u32, SmallRng>::from_seed([33; 32]);
+        implicit.extend(0..100);
+        assert_eq!(implicit.check_invariants(), Ok(()));
+
+        let mut corrupted = implicit.clone();
+        corrupted.root.as_mut().unwrap().left.as_mut().unwrap().size -= 1;
+        assert!(corrupted.check_invariants().is_err());
+
+        let mut corrupted = implicit.clone();
+        corrupted.root.as_mut().unwrap().priority = 0;
+        assert!(corrupted.check_invariants().is_err());
+        assert!(matches!(corrupted.validate(), Err(InvariantViolation::PriorityOrder { parent: 0, .. })));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn treap_insert_rejects_duplicates() {
+        let mut treap = Treap::new();
+
+        assert!(treap.insert(7));
+        assert!(!treap.insert(7));
+        assert!(!treap.insert(7));
+        assert!(treap.insert(8));
+
+        assert!(treap.remove(&7));
+        assert!(!treap.contains(&7));
+        assert!(treap.contains(&8));
+    }
+
+    #[test]
+    fn treap_remove() {
+        let mut rng = SmallRng::seed_from_u64(4);
+        let mut treap = Treap::<u32, SmallRng>::from_seed([4; 32]);
+        let mut expected = BTreeSet::new();
+
+        assert!(!treap.remove(&0));
+
+        for _ in 0..5_000 {
+            let key = rng.gen_range(0..1_000);
+
+            if rng.gen_bool(0.5) {
+                assert_eq!(treap.insert(key), expected.insert(key), "key: {key}");
+            } else {
+                assert_eq!(treap.remove(&key), expected.remove(&key), "key: {key}");
+            }
+
+            #[cfg(feature = "validate")]
+            assert_eq!(treap.validate(), Ok(()));
+        }
+
+        for key in 0..1_000 {
+            assert_eq!(treap.contains(&key), expected.contains(&key), "key: {key}");
+        }
+
+        while let Some(root) = treap.root.as_ref().map(|root| root.key) {
+            assert!(treap.remove(&root));
+            assert!(expected.remove(&root));
+        }
+        assert!(expected.is_empty());
+    }
+
+    #[test]
+    fn treap_len() {
+        let mut rng = SmallRng::seed_from_u64(5);
+        let mut treap = Treap::<u32, SmallRng>::from_seed([5; 32]);
+        let mut expected = BTreeSet::new();
+
+        assert!(treap.is_empty());
+
+        for _ in 0..3_000 {
+            let key = rng.gen_range(0..500);
+
+            match rng.gen_range(0..8) {
+                0..=3 => assert_eq!(treap.insert(key), expected.insert(key)),
+                4 | 5 => assert_eq!(treap.remove(&key), expected.remove(&key)),
+                6 => {
+                    let right = key + rng.gen_range(1..20);
+                    treap.remove_range(&key, &right);
+                    expected.retain(|&x| x < key || x >= right);
+                }
+                _ => {
+                    let greater = treap.split(&key);
+                    let expected_greater = expected.split_off(&key);
+                    assert_eq!(greater.len(), expected_greater.len());
+                    assert_eq!(treap.len(), expected.len());
+
+                    treap = merge(treap, greater);
+                    expected.extend(expected_greater);
+                }
+            }
+
+            assert_eq!(treap.len(), expected.len());
+            assert_eq!(treap.is_empty(), expected.is_empty());
+
+            #[cfg(feature = "validate")]
+            assert_eq!(treap.validate(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn treap_kth_rank() {
+        let mut rng = SmallRng::seed_from_u64(6);
+        let mut treap = Treap::<u32, SmallRng>::from_seed([9; 32]);
+        let mut set = BTreeSet::new();
+
+        assert_eq!(treap.kth(0), None);
+        assert_eq!(treap.rank(&0), 0);
+
+        for _ in 0..2_000 {
+            let key = rng.gen_range(10..300);
+            if rng.gen_bool(0.7) {
+                assert_eq!(treap.insert(key), set.insert(key));
+            } else {
+                assert_eq!(treap.remove(&key), set.remove(&key));
+            }
+
+            let sorted: Vec<u32> = set.iter().copied().collect();
+            let k = rng.gen_range(0..=sorted.len());
+            assert_eq!(treap.kth(k), sorted.get(k), "k: {k}");
+
+            let key = rng.gen_range(0..310);
+            assert_eq!(treap.rank(&key), sorted.partition_point(|&x| x < key), "key: {key}");
+
+            #[cfg(feature = "validate")]
+            assert_eq!(treap.validate(), Ok(()));
+        }
+
+        for (k, key) in set.iter().enumerate() {
+            assert_eq!(treap.kth(k), Some(key));
+            assert_eq!(treap.rank(key), k);
+        }
+        assert_eq!(treap.kth(set.len()), None);
+        assert_eq!(treap.rank(&0), 0);
+        assert_eq!(treap.rank(&u32::MAX), set.len());
+    }
+
+    #[test]
+    fn treap_get_take() {
+        /// Ordered by id only
+        #[derive(Debug)]
+        struct User {
+            id: u32,
+            name: &'static str,
+        }
+
+        impl PartialEq for User {
+            fn eq(&self, other: &Self) -> bool {
+                self.id == other.id
+            }
+        }
+
+        impl Eq for User {}
+
+        impl PartialOrd for User {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for User {
+            fn cmp(&self, other: &Self) -> Ordering {
+                self.id.cmp(&other.id)
+            }
+        }
+
+        impl Borrow<u32> for User {
+            fn borrow(&self) -> &u32 {
+                &self.id
+            }
+        }
+
+        let mut users = Treap::<User, SmallRng>::from_seed([31; 32]);
+        users.extend([(3, "carol"), (1, "alice"), (2, "bob")].map(|(id, name)| User { id, name }));
+
+        assert_eq!(users.get(&2).map(|user| user.name), Some("bob"));
+        assert_eq!(users.get(&User { id: 1, name: "" }).map(|user| user.name), Some("alice"));
+        assert!(users.get(&4).is_none());
+
+        assert_eq!(users.take(&3).map(|user| user.name), Some("carol"));
+        assert!(users.take(&3).is_none());
+        assert!(!users.contains(&3));
+        assert_eq!(users.len(), 2);
+    }
+
+    #[test]
+    fn treap_insert_if_absent() {
+        let mut rng = SmallRng::seed_from_u64(7);
+        let mut treap = Treap::<u32, SmallRng>::from_seed([18; 32]);
+        let mut set = BTreeSet::new();
+
+        assert!(treap.insert_if_absent(5));
+        assert!(!treap.insert_if_absent(5));
+        set.insert(5);
+
+        for _ in 0..5_000 {
+            let key = rng.gen_range(0..1_000);
+            assert_eq!(treap.insert_if_absent(key), set.insert(key), "key: {key}");
+        }
+
+        assert_treap_invariants(&treap.root);
+        assert_eq!(treap.len(), set.len());
+        assert!(treap.into_iter().eq(set.into_iter()));
+    }
+
+    #[test]
+    fn treap_insert_if_absent_single_descent() {
+        use core::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+        static COMPARISONS: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(PartialEq, Eq)]
+        struct Counted(u32);
+
+        impl PartialOrd for Counted {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for Counted {
+            fn cmp(&self, other: &Self) -> Ordering {
+                COMPARISONS.fetch_add(1, Relaxed);
+                self.0.cmp(&other.0)
+            }
+        }
+
+        let mut treap = Treap::<Counted, SmallRng>::from_seed([19; 32]);
+        for key in (0..2_000).step_by(2) {
+            treap.insert(Counted(key));
+        }
+
+        for key in [1, 777, 1_001, 1_999, 2_001, 0, 1_000] {
+            let mut path = 0;
+            let mut node = treap.root.as_deref();
+            while let Some(nd) = node {
+                path += 1;
+                node = match key.cmp(&nd.key.0) {
+                    Ordering::Less => nd.left.as_deref(),
+                    Ordering::Greater => nd.right.as_deref(),
+                    Ordering::Equal => None,
+                };
+            }
+
+            COMPARISONS.store(0, Relaxed);
+            let absent = key % 2 == 1;
+            assert_eq!(treap.insert_if_absent(Counted(key)), absent, "key: {key}");
+            if absent {
+                assert_eq!(COMPARISONS.load(Relaxed), path, "key: {key}");
+            }
+        }
+
+        assert_treap_invariants(&treap.root);
+        assert_eq!(treap.len(), 1_005);
+    }
+
+    #[test]
+    fn treap_set_operations() {
+        let mut rng = SmallRng::seed_from_u64(7);
+
+        for (len_a, len_b) in [(0, 50), (3, 2_000), (500, 500), (2_000, 10), (1_000, 0)] {
+            let a: BTreeSet<u32> = (0..len_a).map(|_| rng.gen_range(0..3_000)).collect();
+            let b: BTreeSet<u32> = (0..len_b).map(|_| rng.gen_range(0..3_000)).collect();
+
+            let treap = |keys: &BTreeSet<u32>, seed| {
+                let mut res = Treap::<u32, SmallRng>::from_seed([seed; 32]);
+                res.extend(keys.iter().copied());
+                res
+            };
+
+            let union = treap(&a, 1).union(treap(&b, 2));
+            assert_treap_invariants(&union.root);
+            assert!(union.into_iter().eq(a.union(&b).copied()));
+
+            let intersection = treap(&a, 3).intersection(treap(&b, 4));
+            assert_treap_invariants(&intersection.root);
+            assert!(intersection.into_iter().eq(a.intersection(&b).copied()));
+
+            let difference = treap(&a, 5).difference(treap(&b, 6));
+            assert_treap_invariants(&difference.root);
+            assert_eq!(difference.len(), a.difference(&b).count());
+            assert!(difference.into_iter().eq(a.difference(&b).copied()));
+
+            assert_eq!(treap(&a, 7).is_subset(&treap(&b, 8)), a.is_subset(&b));
+            assert_eq!(treap(&a, 9).is_disjoint(&treap(&b, 10)), a.is_disjoint(&b));
+        }
+
+        let small = Treap::<u32, SmallRng>::from_seed([11; 32]).union(Treap::from_seed([12; 32]));
+        assert!(small.is_empty());
+
+        let mut a = Treap::<u32, SmallRng>::from_seed([13; 32]);
+        let mut b = Treap::<u32, SmallRng>::from_seed([14; 32]);
+        a.extend([1, 2, 3]);
+        b.extend([0, 1, 2, 3, 4]);
+        assert!(a.is_subset(&b));
+        assert!(!b.is_subset(&a));
+        assert!(!a.is_disjoint(&b));
+        assert_eq!(a.union(b).len(), 5);
+    }
+
+    #[test]
+    fn treap_merge() {
+        let mut rng = SmallRng::seed_from_u64(8);
+
+        for _ in 0..50 {
+            let split_at = rng.gen_range(0..1_000);
+            let keys: BTreeSet<u32> = (0..300).map(|_| rng.gen_range(0..1_000)).collect();
+
+            let mut left = Treap::<u32, SmallRng>::from_seed([21; 32]);
+            let mut right = Treap::<u32, SmallRng>::from_seed([22; 32]);
+            left.extend(keys.range(..split_at).copied());
+            right.extend(keys.range(split_at..).copied());
+
+            let merged = merge(left, right);
+            assert_treap_invariants(&merged.root);
+            assert!(merged.into_iter().eq(keys.iter().copied()));
+
+            let mut left = Treap::<u32, SmallRng>::from_seed([23; 32]);
+            let mut right = Treap::<u32, SmallRng>::from_seed([24; 32]);
+            left.extend(keys.range(split_at..).copied());
+            right.extend(keys.range(..split_at).copied());
+            right.insert(split_at);
+            left.insert(split_at);
+
+            let merged = merge(left, right);
+            assert_treap_invariants(&merged.root);
+            assert!(merged.into_iter().eq(keys.iter().copied().chain([split_at]).collect::<BTreeSet<_>>()));
+        }
+
+        let mut left = Treap::<u32, SmallRng>::from_seed([25; 32]);
+        let mut right = Treap::<u32, SmallRng>::from_seed([26; 32]);
+        left.extend([1, 2]);
+        right.extend([3, 4]);
+        assert!(merge_unchecked(left, right).into_iter().eq(1..=4));
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "keys of left are not less than keys of right")]
+    fn treap_merge_unchecked_unordered() {
+        let mut left = Treap::<u32, SmallRng>::from_seed([27; 32]);
+        let mut right = Treap::<u32, SmallRng>::from_seed([28; 32]);
+        left.insert(5);
+        right.insert(5);
+        merge_unchecked(left, right);
+    }
+
+    #[test]
+    fn treap_borrowed_lookups() {
+        let mut words = Treap::<String, SmallRng>::from_seed([29; 32]);
+        words.extend(["apple", "banana", "cherry", "date", "fig"].map(String::from));
+
+        assert!(words.contains("banana"));
+        assert!(!words.contains("grape"));
+        assert_eq!(words.floor("c").map(String::as_str), Some("banana"));
+        assert_eq!(words.ceil("c").map(String::as_str), Some("cherry"));
+        assert_eq!(words.lower("cherry").map(String::as_str), Some("banana"));
+        assert_eq!(words.higher("cherry").map(String::as_str), Some("date"));
+        assert_eq!(words.rank("d"), 3);
+        assert_eq!(words.count_range("b", "e"), 3);
+        assert_eq!(words.count_in::<str, _>((Bound::Excluded("banana"), Bound::Unbounded)), 3);
+        assert!(words.range::<str, _>((Bound::Included("b"), Bound::Excluded("e"))).eq(["banana", "cherry", "date"]));
+
+        assert!(words.remove("date"));
+        assert!(!words.remove("date"));
+        let greater = words.split("c");
+        assert!(greater.into_iter().eq(["cherry", "fig"]));
+        words.remove_range("a", "b");
+        assert!(words.into_iter().eq(["banana"]));
+
+        let mut bytes = Treap::<Vec<u8>, SmallRng>::from_seed([30; 32]);
+        bytes.extend([b"abc".to_vec(), b"abd".to_vec(), b"b".to_vec()]);
+        assert!(bytes.contains(&b"abd"[..]));
+        assert!(!bytes.contains(&b"ab"[..]));
+        assert_eq!(bytes.ceil(&b"ab"[..]), Some(&b"abc".to_vec()));
+        assert_eq!(bytes.drain_range::<[u8], _>((Bound::Excluded(&b"abc"[..]), Bound::Unbounded)).count(), 2);
+        assert_eq!(bytes.len(), 1);
+    }
+
+    /// Hands out strictly decreasing priorities, so sorted keys form a chain
+    struct DescendingRng(u32);
+
+    impl RngCore for DescendingRng {
+        fn next_u32(&mut self) -> u32 {
+            self.0 -= 1;
+            self.0
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            self.next_u32() as u64
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            for chunk in dest.chunks_mut(4) {
+                let bytes = self.next_u32().to_le_bytes();
+                chunk.copy_from_slice(&bytes[..chunk.len()]);
+            }
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    impl SeedableRng for DescendingRng {
+        type Seed = [u8; 4];
+
+        fn from_seed(seed: [u8; 4]) -> Self {
+            Self(u32::from_le_bytes(seed))
+        }
+    }
+
+    /// Hands out the given priorities in order, can not be seeded
+    struct ScriptedRng(std::vec::IntoIter<u32>);
+
+    impl RngCore for ScriptedRng {
+        fn next_u32(&mut self) -> u32 {
+            self.0.next().expect("ran out of priorities")
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            self.next_u32() as u64
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            for chunk in dest.chunks_mut(4) {
+                let bytes = self.next_u32().to_le_bytes();
+                chunk.copy_from_slice(&bytes[..chunk.len()]);
+            }
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    /// Keys in pre-order, None for every empty child
+    fn preorder<K: Ord + Copy, V>(root: &Link<K, V>) -> Vec<Option<K>> {
+        let mut res = Vec::new();
+        let mut stack = vec![root.as_deref()];
+        while let Some(node) = stack.pop() {
+            res.push(node.map(|node| node.key));
+            if let Some(node) = node {
+                stack.push(node.right.as_deref());
+                stack.push(node.left.as_deref());
+            }
+        }
+
+        res
+    }
+
+    #[test]
+    fn with_rng_without_seeding() {
+        let priorities = vec![3, 5, 1, 4, 2];
+        let mut treap = Treap::with_rng(ScriptedRng(priorities.into_iter()));
+        treap.extend(1..=5);
+        //     2
+        //   1   4
+        //      3 5
+        assert_eq!(preorder(&treap.root), [Some(2), Some(1), None, None, Some(4), Some(3), None, None, Some(5), None, None]);
+
+        treap.remove_range(&3, &5);
+        assert_eq!(preorder(&treap.root), [Some(2), Some(1), None, None, Some(5), None, None]);
+        treap = merge(treap, Treap::with_rng(ScriptedRng(Vec::new().into_iter())));
+        assert!(treap.into_iter().eq([1, 2, 5]));
+
+        let mut implicit = ImplicitTreap::with_rng(ScriptedRng(vec![1, 3, 2].into_iter()));
+        implicit.extend([10, 20, 30]);
+        assert_eq!(implicit.root.as_ref().map(|root| root.value), Some(20));
+        implicit.remove_range(0, 2);
+        assert_eq!(implicit.to_vec(), [30]);
+
+        let mut rng = SmallRng::seed_from_u64(9);
+        let mut first = Treap::with_rng(&mut rng);
+        first.extend(0..10);
+        assert_eq!(first.len(), 10);
+        let mut second = Treap::with_rng(&mut rng);
+        second.extend(0..10);
+        assert_eq!(second.len(), 10);
+    }
+
+    #[test]
+    fn split_with_borrowed_rng() {
+        let mut rng = SmallRng::seed_from_u64(3);
+        let mut other_rng = SmallRng::seed_from_u64(4);
+
+        let mut treap = Treap::with_rng(&mut rng);
+        treap.extend(0..100);
+        let mut greater = treap.split_with(&60, &mut other_rng);
+        greater.insert(200);
+        let middle = treap.extract_range_with(&20, &40, ScriptedRng(Vec::new().into_iter()));
+        assert!(treap.into_iter().eq((0..20).chain(40..60)));
+        assert!(middle.into_iter().eq(20..40));
+        assert!(greater.into_iter().eq((60..100).chain([200])));
+
+        let mut implicit = ImplicitTreap::with_rng(&mut rng);
+        implicit.extend(0..100);
+        let mut greater = implicit.split_with(60, &mut other_rng);
+        greater.insert_before(0, -1);
+        let middle = implicit.extract_range_with(20, 40, ScriptedRng(Vec::new().into_iter()));
+        assert!(implicit.iter().copied().eq((0..20).chain(40..60)));
+        assert_eq!(middle.to_vec(), (20..40).collect::<Vec<_>>());
+        assert!(greater.iter().copied().eq([-1].into_iter().chain(60..100)));
+    }
+
+    #[test]
+    fn treap_split_merge_degenerate() {
+        const N: u32 = 200_000;
+
+        let mut treap = Treap::with_rng(DescendingRng(u32::MAX));
+        treap.init_with_sorted(0..N);
+
+        for key in [N / 2, 1, N - 1, 3 * N / 4, N / 3] {
+            let greater = treap.split(&key);
+            assert_eq!(greater.first(), Some(&key));
+            assert_eq!(treap.last(), Some(&(key - 1)));
+            assert_eq!(treap.len() + greater.len(), N as usize);
+            treap = merge_unchecked(treap, greater);
+        }
+
+        assert!(treap.into_iter().eq(0..N));
+    }
+
+    #[test]
+    fn treap_retain() {
+        let mut rng = SmallRng::seed_from_u64(10);
+        let keys: Vec<u32> = (0..5_000).map(|_| rng.gen_range(0..20_000)).collect();
+        let mut treap = Treap::<u32, SmallRng>::from_seed([34; 32]);
+        treap.extend(keys.iter().copied());
+        let mut expected: BTreeSet<u32> = keys.into_iter().collect();
+
+        treap.retain(|key| key % 2 == 0);
+        expected.retain(|key| key % 2 == 0);
+        assert_treap_invariants(&treap.root);
+        assert_eq!(treap.len(), expected.len());
+        assert!(treap.range::<u32, _>(..).eq(expected.iter()));
+
+        treap.retain(|_| false);
+        assert!(treap.is_empty());
+    }
+
+    #[test]
+    fn treap_replace_range() {
+        let mut treap = Treap::<u32, SmallRng>::from_seed([38; 32]);
+        treap.extend((0..100).map(|key| key * 2));
+
+        treap.replace_range(&50, &150, [51, 149, 100, 77, 51]);
+        assert_treap_invariants(&treap.root);
+        let expected: Vec<u32> = (0..25).map(|key| key * 2)
+            .chain([51, 77, 100, 149])
+            .chain((75..100).map(|key| key * 2))
+            .collect();
+        assert!(treap.range::<u32, _>(..).eq(expected.iter()));
+
+        treap.replace_range(&7, &7, []);
+        assert!(treap.range::<u32, _>(..).eq(expected.iter()));
+
+        treap.replace_range(&0, &1_000, []);
+        assert!(treap.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "replaced range is reversed")]
+    fn treap_replace_range_reversed() {
+        let mut treap = Treap::<u32, SmallRng>::from_seed([39; 32]);
+        treap.extend(0..10);
+        treap.replace_range(&6, &3, []);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of the replaced range")]
+    fn treap_replace_range_out_of_range() {
+        let mut treap = Treap::<u32, SmallRng>::from_seed([39; 32]);
+        treap.extend(0..10);
+        treap.replace_range(&3, &6, [4, 6]);
+    }
+
+    #[test]
+    fn treap_split_at_rank() {
+        let mut rng = SmallRng::seed_from_u64(11);
+
+        for _ in 0..50 {
+            let mut treap = Treap::<u32, SmallRng>::from_seed(rng.gen());
+            treap.extend((0..rng.gen_range(0..300)).map(|_| rng.gen_range(0..1_000)));
+            let keys: Vec<u32> = treap.range::<u32, _>(..).copied().collect();
+
+            let k = rng.gen_range(0..keys.len() + 5);
+            let mut less = Treap::<u32, SmallRng>::from_seed(rng.gen());
+            less.extend(keys.iter().copied());
+            let greater = less.split_at_rank(k);
+            assert_treap_invariants(&less.root);
+            assert_treap_invariants(&greater.root);
+            assert!(less.range::<u32, _>(..).eq(&keys[..k.min(keys.len())]));
+            assert!(greater.range::<u32, _>(..).eq(&keys[k.min(keys.len())..]));
+
+            treap.truncate_to_smallest(k);
+            assert!(treap.into_iter().eq(keys.iter().copied().take(k)));
+        }
+
+        let mut treap = Treap::<u32, SmallRng>::from_seed([43; 32]);
+        treap.extend(0..10);
+        assert_eq!(treap.split_at_rank(0).len(), 10);
+        assert!(treap.is_empty());
+    }
+
+    #[test]
+    fn treap_clear() {
+        let mut treap = Treap::<u32, SmallRng>::from_seed([19; 32]);
+        let mut same_seed = Treap::<u32, SmallRng>::from_seed([19; 32]);
+
+        treap.extend(0..100);
+        same_seed.extend(0..100);
+        treap.clear();
+        same_seed.clear();
+        assert!(treap.is_empty());
+        assert_eq!(treap.len(), 0);
+        assert!(!treap.contains(&5));
+
+        treap.extend(50..60);
+        same_seed.extend(50..60);
+        assert_eq!(treap.len(), 10);
+        assert_eq!(hash_of(&treap.root), hash_of(&same_seed.root));
+
+        let mut implicit = ImplicitTreap::<u32, SmallRng>::from_seed([20; 32]);
+        implicit.extend(0..100);
+        implicit.clear();
+        assert_eq!(implicit.iter().len(), 0);
+
+        implicit.extend(0..3);
+        assert_eq!(implicit.to_vec(), [0, 1, 2]);
+    }
+
+    fn hash_of<K: Ord + Hash>(node: &Link<K>) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        hash_structure(node, &mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn treap_first_last_pop() {
+        let mut rng = SmallRng::seed_from_u64(12);
+        let mut treap = Treap::<u32, SmallRng>::from_seed([12; 32]);
+        let mut set = BTreeSet::new();
+
+        assert_eq!(treap.first(), None);
+        assert_eq!(treap.last(), None);
+        assert_eq!(treap.pop_first(), None);
+        assert_eq!(treap.pop_last(), None);
+
+        for _ in 0..1_000 {
+            let key = rng.gen_range(0..10_000);
+            treap.insert(key);
+            set.insert(key);
+        }
+
+        assert_eq!(treap.first(), set.first());
+        assert_eq!(treap.last(), set.last());
+
+        for _ in 0..10 {
+            assert_eq!(treap.pop_last(), set.pop_last());
+            assert_eq!(treap.len(), set.len());
+        }
+
+        let expected_len = set.len();
+        let drained: Vec<u32> = core::iter::from_fn(|| treap.pop_first()).collect();
+        assert_eq!(drained.len(), expected_len);
+        assert!(drained.iter().eq(set.iter()));
+        assert!(treap.is_empty());
+        assert_eq!(treap.first(), None);
+    }
+
+    #[test]
+    fn treap_range() {
+        let mut rng = SmallRng::seed_from_u64(13);
+        let mut treap = Treap::<i32, SmallRng>::from_seed([13; 32]);
+        let mut set = BTreeSet::new();
+
+        assert_eq!(treap.range(..).next(), None);
+
+        for _ in 0..300 {
+            let key = rng.gen_range(-100..100);
+            treap.insert(key);
+            set.insert(key);
+        }
+
+        let bound = |rng: &mut SmallRng| match rng.gen_range(0..3) {
+            0 => Bound::Included(rng.gen_range(-110..110)),
+            1 => Bound::Excluded(rng.gen_range(-110..110)),
+            _ => Bound::Unbounded,
+        };
+
+        for _ in 0..2_000 {
+            let (start, end) = (bound(&mut rng), bound(&mut rng));
+            let empty = match (start, end) {
+                (Bound::Included(s) | Bound::Excluded(s), Bound::Included(e) | Bound::Excluded(e)) => {
+                    s > e || (s == e && !matches!((start, end), (Bound::Included(_), Bound::Included(_))))
+                }
+                _ => false,
+            };
+
+            if empty {
+                assert_eq!(treap.range((start, end)).next(), None, "{start:?}, {end:?}");
+                continue;
+            }
+
+            let mut range = treap.range((start, end));
+            let mut expected = set.range((start, end));
+            assert_eq!(range.len(), expected.clone().count());
+
+            loop {
+                let (got, want) = if rng.gen_bool(0.5) {
+                    (range.next(), expected.next())
+                } else {
+                    (range.next_back(), expected.next_back())
+                };
+
+                assert_eq!(got, want, "{start:?}, {end:?}");
+                if got.is_none() {
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn treap_into_iter_drain_range() {
+        let mut rng = SmallRng::seed_from_u64(14);
+        let mut treap = Treap::<String, SmallRng>::from_seed([15; 32]);
+        let mut set = BTreeSet::new();
+
+        for _ in 0..500 {
+            let key = format!("{:03}", rng.gen_range(0..1_000));
+            treap.insert(key.clone());
+            set.insert(key);
+        }
+
+        let (left, right) = ("300".to_string(), "600".to_string());
+        let drained: Vec<String> = treap.drain_range(left.clone()..right.clone()).collect();
+        let expected: Vec<String> = set.range(left.clone()..right.clone()).cloned().collect();
+        assert_eq!(drained, expected);
+        set.retain(|key| *key < left || *key >= right);
+        assert_eq!(treap.len(), set.len());
+
+        let dropped = treap.drain_range((Bound::Excluded("100".to_string()), Bound::Included("200".to_string())));
+        assert_eq!(dropped.len(), set.range("101".to_string()..="200".to_string()).count());
+        drop(dropped);
+        set.retain(|key| key.as_str() <= "100" || key.as_str() > "200");
+
+        assert_eq!(treap.drain_range("900".to_string().."800".to_string()).count(), 0);
+        assert_eq!(treap.len(), set.len());
+        assert!(treap.into_iter().eq(set.into_iter()));
+    }
+
+    #[test]
+    fn treap_into_iter_deep() {
+        let mut treap = Treap::<u32, SmallRng>::from_seed([16; 32]);
+        for key in 0..1_000_000 {
+            treap.insert(key);
+        }
+
+        let mut iter = treap.into_iter();
+        assert_eq!(iter.len(), 1_000_000);
+        assert!(iter.by_ref().take(10).eq(0..10));
+    }
+
+    #[test]
+    fn treap_floor_ceil() {
+        let mut rng = SmallRng::seed_from_u64(15);
+        let mut treap = Treap::<i32, SmallRng>::from_seed([11; 32]);
+        let mut set = BTreeSet::new();
+
+        assert_eq!(treap.floor(&0), None);
+        assert_eq!(treap.ceil(&0), None);
+        assert_eq!(treap.lower(&0), None);
+        assert_eq!(treap.higher(&0), None);
+
+        for _ in 0..2_000 {
+            let key = rng.gen_range(-50..50);
+            if rng.gen_bool(0.6) {
+                treap.insert(key);
+                set.insert(key);
+            } else {
+                treap.remove(&key);
+                set.remove(&key);
+            }
+
+            let x = rng.gen_range(-60..60);
+            assert_eq!(treap.floor(&x), set.range(..=x).next_back(), "x: {x}");
+            assert_eq!(treap.ceil(&x), set.range(x..).next(), "x: {x}");
+            assert_eq!(treap.lower(&x), set.range(..x).next_back(), "x: {x}");
+            assert_eq!(treap.higher(&x), set.range((Bound::Excluded(x), Bound::Unbounded)).next(), "x: {x}");
+
+            #[cfg(feature = "validate")]
+            assert_eq!(treap.validate(), Ok(()));
+        }
+
+        treap.insert(10);
+        set.insert(10);
+        assert_eq!(treap.floor(&10), Some(&10));
+        assert_eq!(treap.ceil(&10), Some(&10));
+        assert_eq!(treap.lower(&10), set.range(..10).next_back());
+        assert_eq!(treap.higher(&10), set.range(11..).next());
+        assert_eq!(treap.higher(&i32::MAX), None);
+        assert_eq!(treap.lower(&i32::MIN), None);
+    }
+
+    #[test]
+    fn treap_count_range() {
+        let mut rng = SmallRng::seed_from_u64(16);
+        let mut treap = Treap::<i32, SmallRng>::from_seed([10; 32]);
+        let mut set = BTreeSet::new();
+
+        assert_eq!(treap.count_in(..), 0);
+        assert_eq!(treap.count_range(&0, &10), 0);
+
+        for _ in 0..1_000 {
+            let key = rng.gen_range(-100..100);
+            treap.insert(key);
+            set.insert(key);
+
+            let left = rng.gen_range(-120..120);
+            let right = rng.gen_range(-120..120);
+            let expected = if left < right { set.range(left..right).count() } else { 0 };
+            assert_eq!(treap.count_range(&left, &right), expected, "{left}..{right}");
+
+            if left <= right {
+                assert_eq!(treap.count_in(left..=right), set.range(left..=right).count());
+                assert_eq!(treap.count_in(left..right), expected);
+            }
+            assert_eq!(treap.count_in(..right), set.range(..right).count());
+            assert_eq!(treap.count_in(left..), set.range(left..).count());
+            assert_eq!(treap.count_in((Bound::Excluded(left), Bound::Unbounded)),
+                set.range((Bound::Excluded(left), Bound::Unbounded)).count());
+        }
+
+        assert_eq!(treap.count_in(..), set.len());
+        assert_eq!(treap.count_in(200..300), 0);
+        assert_eq!(treap.count_in(-300..-200), 0);
+        assert_eq!(treap.count_in(5..5), 0);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn implicit_treap_works() {
+        let mut treap = ImplicitTreap::new();
+        assert!(treap.is_empty());
+           
+        treap.insert_before(0, 5); // 5
+        treap.insert_before(1, 3); // 5 3
+        treap.insert_before(1, 4); // 5 4 3
+        assert_eq!(treap.len(), 3);
+        assert_eq!(treap.get(0), Some(&5));
+        assert_eq!(treap.get_mut(1), Some(&mut 4));
+        treap.insert_before(0, 2); // 2 5 4 3
+        assert_eq!(treap.get(0), Some(&2));
+        assert_eq!(treap.get_mut(1), Some(&mut 5));
+        treap.insert_before(4, 1); // 2 5 4 3 1
+        assert_eq!(treap.get(4), Some(&1));
+        assert_eq!(treap.len(), 5);
+        assert!(!treap.is_empty());
+        assert_eq!(treap.check_invariants(), Ok(()));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn implicit_treap_from_slice() {
+        let values: Vec<u32> = (0..1000).map(|value| value * 7 % 1000).collect();
+        let treap = ImplicitTreap::build(&values);
+
+        for index in [0, 1, 2, 499, 500, 998, 999] {
+            assert_eq!(treap.get(index), Some(&values[index]), "index: {index}");
+        }
+        assert_eq!(treap.get(1000), None);
+        assert_eq!(treap.to_vec(), values);
+
+        let mut treap = ImplicitTreap::<u32, SmallRng>::from_seed([3; 32]).with_merge(|a, b| a + b);
+        treap.init_with(&values);
+        assert_eq!(treap.query_range(10, 20), values[10..20].iter().sum::<u32>());
+        treap.init_with(&[]);
+        assert!(treap.to_vec().is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn collect_and_extend() {
+        let mut treap: Treap<i32> = (0..10).rev().collect();
+        treap.extend([20, 15, 5]);
+        assert!(treap.into_iter().eq((0..10).chain([15, 20])));
+
+        let mut implicit: ImplicitTreap<i32> = (0..10).rev().collect();
+        implicit.extend([20, 15, 5]);
+        assert_eq!(implicit.iter().len(), 13);
+        assert!(implicit.iter().copied().eq((0..10).rev().chain([20, 15, 5])));
+
+        implicit.insert_before(0, -1);
+        assert_eq!(implicit.get(0), Some(&-1));
+        assert_eq!(implicit.get(13), Some(&5));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn implicit_treap_iter() {
+        let mut treap = ImplicitTreap::new();
+        assert_eq!(treap.to_vec(), Vec::<i32>::new());
+           
+        treap.insert_before(0, 5); // 5
+        treap.insert_before(1, 3); // 5 3
+        treap.insert_before(1, 4); // 5 4 3
+        treap.insert_before(0, 2); // 2 5 4 3
+        treap.insert_before(4, 1); // 2 5 4 3 1
+        assert_eq!(treap.to_vec(), vec![2, 5, 4, 3, 1]);
+        assert_eq!(treap.iter().len(), 5);
+
+        treap.reverse_range(1, 4); // 2 3 4 5 1
+        assert_eq!(treap.iter().copied().collect::<Vec<_>>(), vec![2, 3, 4, 5, 1]);
+        assert_eq!(treap.iter().position(|&value| value == 5), Some(3));
+
+        let mut sum = 0;
+        for value in &treap {
+            sum += value;
+        }
+        assert_eq!(sum, 15);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn implicit_treap_flush() {
+        let mut treap = ImplicitTreap::new().with_lazy(|value: &i32, delta, _| value + delta);
+        
+        for (index, value) in [1, 2, 3, 4].into_iter().enumerate() {
+            treap.insert_before(index, value);
+        }
+
+        treap.add_range(0, 4, 10);
+        treap.add_range(1, 3, 100);
+        assert_eq!(treap.to_vec(), vec![11, 112, 113, 14]);
+        treap.reverse_range(0, 3);
+        assert!(treap.iter().copied().eq([113, 112, 11, 14]));
+        let shared = &treap;
+        assert!(shared.into_iter().zip(shared.iter()).all(|(a, b)| a == b));
+        treap.reverse_range(0, 3);
+        treap.flush();
+        assert_eq!(treap.to_vec(), vec![11, 112, 113, 14]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn implicit_treap_index() {
+        let mut treap = ImplicitTreap::new();
+        
+        for (index, value) in [5, 4, 3].into_iter().enumerate() {
+            treap.insert_before(index, value);
+        }
+
+        assert_eq!(treap[0], 5);
+        assert_eq!(treap[2], 3);
+        treap[1] = 7;
+        treap[2] += 10;
+        assert_eq!(treap[1], 7);
+        assert_eq!(treap[2], 13);
+
+        treap.reverse_range(0, 3); // 13 7 5
+        assert_eq!(treap[0], 13);
+        assert_eq!(treap[2], 5);
+    }
+
+    #[test]
+    fn implicit_treap_get_with_pending_adds() {
+        let mut values: Vec<i64> = (0..1000).collect();
+        let mut treap = ImplicitTreap::<i64, SmallRng>::from_seed([60; 32])
+            .with_lazy(|value, delta, len| value + delta * len as i64);
+        treap.init_with(&values);
+
+        treap.add_range(0, 1000, 1);
+        treap.reverse_range(100, 700);
+        treap.add_range(300, 400, -5);
+        values.iter_mut().for_each(|value| *value += 1);
+        values[100..700].reverse();
+        values[300..400].iter_mut().for_each(|value| *value -= 5);
+
+        for (index, &value) in values.iter().enumerate() {
+            assert_eq!(treap.get(index), Some(&value), "index: {index}");
+        }
+        assert_eq!(treap.try_get(1000), Err(IndexError { index: 1000, len: 1000 }));
+
+        // values read above are kept resolved, a new add has to reach them
+        treap.add_range(200, 800, 10);
+        treap[250] -= 3;
+        values[200..800].iter_mut().for_each(|value| *value += 10);
+        values[250] -= 3;
+
+        for (index, &value) in values.iter().enumerate() {
+            assert_eq!(treap.get(index), Some(&value), "index: {index}");
+            assert_eq!(treap[index], value, "index: {index}");
+        }
+
+        treap.flush();
+        assert_eq!(treap.to_vec(), values);
+    }
+
+    #[test]
+    fn implicit_treap_index_mut_updates_aggregates() {
+        let mut values: Vec<u64> = (0..1000).collect();
+        let mut treap = ImplicitTreap::<u64, SmallRng>::from_seed([59; 32])
+            .with_merge(|a, b| a + b)
+            .with_lazy(|value, delta, len| value + delta * len as u64);
+        treap.init_with(&values);
+
+        treap[500] += 1_000_000;
+        values[500] += 1_000_000;
+        assert_eq!(treap.query_range(400, 600), values[400..600].iter().sum::<u64>());
+
+        treap.add_range(450, 550, 3);
+        values[450..550].iter_mut().for_each(|value| *value += 3);
+        treap[10] = 7;
+        *treap.get_mut(520).unwrap() *= 2;
+        values[10] = 7;
+        values[520] *= 2;
+        treap.reverse_range(0, 600);
+        values[..600].reverse();
+
+        assert_eq!(treap.query_range(0, 1000), values.iter().sum::<u64>());
+        for (left, right) in [(0, 1), (589, 590), (79, 81), (100, 599), (300, 1000)] {
+            assert_eq!(treap.query_range(left, right), values[left..right].iter().sum::<u64>(), "left: {left}, right: {right}");
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    #[should_panic(expected = "index out of bounds")]
+    fn implicit_treap_index_out_of_bounds() {
+        let mut treap = ImplicitTreap::new();
+        treap.insert_before(0, 1);
+        let _ = treap[1];
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn implicit_treap_remove() {
+        let mut treap = ImplicitTreap::new();
+        
+        for (index, value) in [1, 2, 3, 4, 5].into_iter().enumerate() {
+            treap.insert_before(index, value);
+        }
+
+        assert_eq!(treap.remove(0), Some(1)); // 2 3 4 5
+        assert_eq!(treap.remove(1), Some(3)); // 2 4 5
+        assert_eq!(treap.remove(2), Some(5)); // 2 4
+        assert_eq!(treap.remove(2), None);
+        assert_eq!(treap.remove(usize::MAX), None);
+        assert_eq!(treap.len(), 2);
+        assert_eq!(treap.get(0), Some(&2));
+        assert_eq!(treap.get(1), Some(&4));
+        assert_eq!(treap.get(2), None);
+    }
+
+    #[test]
+    fn implicit_treap_drain() {
+        let mut treap = ImplicitTreap::<u32, SmallRng>::from_seed([14; 32])
+            .with_lazy(|value, delta, _| value + delta);
+        let mut expected: Vec<u32> = (0..20).collect();
+        for (index, &value) in expected.iter().enumerate() {
+            treap.insert_before(index, value);
+        }
+
+        treap.reverse_range(3, 15);
+        expected[3..15].reverse();
+        treap.add_range(5, 12, 100);
+        expected[5..12].iter_mut().for_each(|value| *value += 100);
+
+        let drained: Vec<u32> = treap.drain(4, 10).collect();
+        assert_eq!(drained, expected.drain(4..10).collect::<Vec<_>>());
+        assert_eq!(treap.drain(7, 7).count(), 0);
+        assert_eq!(treap.len(), expected.len());
+        treap.flush();
+        assert_eq!(treap.to_vec(), expected);
+
+        assert_eq!(treap.drain(0, expected.len()).collect::<Vec<_>>(), expected);
+        assert_eq!(treap.to_vec(), []);
+        assert!(treap.is_empty());
+    }
+
+    #[test]
+    fn implicit_treap_small_stack() {
+        let worker = std::thread::Builder::new()
+            .stack_size(256 * 1024)
+            .spawn(|| {
+                const N: usize = 5_000_000;
+
+                let values: Vec<u32> = (0..N as u32).collect();
+                let mut treap = ImplicitTreap::<u32, SmallRng>::from_seed([36; 32]);
+                treap.init_with(&values);
+                let mut treap = treap.with_merge(|a, b| *a.max(b));
+
+                for index in [N / 2, 1, N - 1, N / 3, 2 * N / 3] {
+                    let mut greater = treap.split(index);
+                    assert_eq!(greater.get(0), Some(&(index as u32)));
+                    treap.append(&mut greater);
+                }
+
+                treap.reverse_range(1_000, N - 1_000);
+                assert_eq!(treap.get(1_000), Some(&(N as u32 - 1_001)));
+                assert_eq!(treap.query_range(0, N), N as u32 - 1);
+                assert_eq!(treap.remove(N / 2), Some(N as u32 / 2 - 1));
+                assert_eq!(treap.check_invariants(), Ok(()));
+            })
+            .unwrap();
+
+        worker.join().unwrap();
+    }
+
+    #[test]
+    fn implicit_treap_retain() {
+        let mut treap = ImplicitTreap::<u32, SmallRng>::from_seed([35; 32])
+            .with_merge(|a, b| a + b)
+            .with_lazy(|value, delta, len| value + delta * len as u32);
+        let mut expected: Vec<u32> = (0..1_000).map(|value| value * 7 % 1_000).collect();
+        treap.extend(expected.iter().copied());
+
+        treap.reverse_range(100, 600);
+        expected[100..600].reverse();
+        treap.add_range(300, 800, 1);
+        expected[300..800].iter_mut().for_each(|value| *value += 1);
+
+        treap.retain(|value| value % 3 != 0);
+        expected.retain(|value| value % 3 != 0);
+        assert_eq!(treap.check_invariants(), Ok(()));
+        assert_eq!(treap.to_vec(), expected);
+        assert_eq!(treap.query_range(0, expected.len()), expected.iter().sum::<u32>());
+    }
+
+    #[test]
+    fn implicit_treap_reverse_range() {
+        let mut treap = ImplicitTreap::<usize, SmallRng>::from_seed([7; 32]);
+        let mut values: Vec<usize> = (0..10).collect();
+
+        for (index, value) in values.iter().enumerate() {
+            treap.insert_before(index, *value);
+        }
+
+        for (l, r) in [(2, 7), (0, 10), (4, 5), (1, 4), (3, 9), (0, 6)] {
+            treap.reverse_range(l, r);
+            values[l..r].reverse();
+
+            for (index, value) in values.iter().enumerate() {
+                assert_eq!(treap.get(index), Some(value), "index: {index}");
+            }
+        }
+
+        treap.insert_before(5, 42);
+        values.insert(5, 42);
+        treap.remove(2);
+        values.remove(2);
+
+        for (index, value) in values.iter_mut().enumerate() {
+            assert_eq!(treap.get_mut(index), Some(value), "index: {index}");
+        }
+    }
+
+    #[test]
+    fn implicit_treap_add_range() {
+        let mut rng = SmallRng::seed_from_u64(17);
+        let mut treap = ImplicitTreap::<i64, SmallRng>::from_seed([1; 32])
+            .with_merge(|a, b| a + b)
+            .with_lazy(|aggregate, delta, len| aggregate + delta * len as i64);
+        let mut values = Vec::new();
+
+        for _ in 0..500 {
+            match rng.gen_range(0..4) {
+                0 => {
+                    let index = rng.gen_range(0..=values.len());
+                    let value = rng.gen_range(-100..100);
+                    treap.insert_before(index, value);
+                    values.insert(index, value);
+                }
+                1 if !values.is_empty() => {
+                    let index = rng.gen_range(0..values.len());
+                    treap.remove(index);
+                    values.remove(index);
+                }
+                _ if !values.is_empty() => {
+                    let left = rng.gen_range(0..values.len());
+                    let right = rng.gen_range(left + 1..=values.len());
+                    let delta = rng.gen_range(-100..100);
+                    treap.add_range(left, right, delta);
+                    values[left..right].iter_mut().for_each(|value| *value += delta);
+
+                    let left = rng.gen_range(0..values.len());
+                    let right = rng.gen_range(left + 1..=values.len());
+                    assert_eq!(treap.query_range(left, right), values[left..right].iter().sum::<i64>());
+
+                    let index = rng.gen_range(0..values.len());
+                    assert_eq!(treap.get(index), Some(&values[index]));
+                }
+                _ => {}
+            }
+
+            assert_eq!(treap.len(), values.len());
+            #[cfg(feature = "validate")]
+            assert_eq!(treap.validate(), Ok(()));
+        }
+
+        for (index, value) in values.iter().enumerate() {
+            assert_eq!(treap.get(index), Some(value), "index: {index}");
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn implicit_treap_query_range() {
+        let mut treap = ImplicitTreap::new().with_merge(|a: &i32, b| a + b);
+        let mut values = Vec::new();
+
+        for (index, value) in [(0, 5), (1, 3), (1, 4), (0, 2), (4, 1), (2, 7)] {
+            treap.insert_before(index, value);
+            values.insert(index, value);
+        }
+
+        treap.remove(3);
+        values.remove(3);
+        treap.remove_range(0, 2);
+        values.drain(0..2);
+        treap.insert_before(1, 6);
+        values.insert(1, 6);
+
+        for l in 0..values.len() {
+            for r in l + 1..=values.len() {
+                assert_eq!(treap.query_range(l, r), values[l..r].iter().sum::<i32>(), "l: {l}, r: {r}");
+            }
+        }
+    }
+
+    #[test]
+    fn implicit_treap_append_split_off() {
+        let mut rng = SmallRng::seed_from_u64(18);
+        let values: Vec<u32> = (0..300).collect();
+        let mut treap = ImplicitTreap::<u32, SmallRng>::from_seed([1; 32]).with_merge(|a, b| *a.max(b));
+        treap.init_with(&values);
+
+        for _ in 0..100 {
+            let index = rng.gen_range(0..=values.len());
+            let mut tail = treap.split_off(index);
+            assert_eq!(treap.to_vec(), values[..index]);
+            assert_eq!(tail.to_vec(), values[index..]);
+
+            treap.append(&mut tail);
+            assert_eq!(treap.to_vec(), values);
+            assert!(tail.iter().next().is_none());
+        }
+
+        assert_eq!(treap.query_range(0, values.len()), 299);
+    }
+
+    #[test]
+    fn implicit_treap_rotate() {
+        let mut values: Vec<u32> = (0..50).collect();
+        let mut treap = ImplicitTreap::<u32, SmallRng>::from_seed([12; 32]).with_merge(|a, b| a + b);
+        treap.init_with(&values);
+
+        for k in [0, 1, 7, 49, 50, 123, usize::MAX] {
+            treap.rotate_left(k);
+            values.rotate_left(k % 50);
+            assert_eq!(treap.to_vec(), values, "rotate_left({k})");
+
+            treap.rotate_right(k);
+            values.rotate_right(k % 50);
+            assert_eq!(treap.to_vec(), values, "rotate_right({k})");
+        }
+
+        treap.reverse_range(10, 40);
+        values[10..40].reverse();
+        treap.rotate_right(17);
+        values.rotate_right(17);
+        assert_eq!(treap.to_vec(), values);
+        assert_eq!(treap.query_range(0, 50), 1225);
+
+        let mut empty = ImplicitTreap::<u32, SmallRng>::from_seed([13; 32]);
+        empty.rotate_left(5);
+        empty.rotate_right(5);
+        assert!(empty.to_vec().is_empty());
+    }
+
+    #[test]
+    fn append_in_place() {
+        let mut treap = Treap::<u32, SmallRng>::from_seed([46; 32]);
+        let mut implicit = ImplicitTreap::<u32, SmallRng>::from_seed([47; 32]);
+
+        for chunk in 0..10 {
+            let mut keys = Treap::<u32, SmallRng>::from_seed([chunk as u8; 32]);
+            keys.extend(chunk * 100..(chunk + 1) * 100);
+            treap.append(&mut keys);
+            assert!(keys.is_empty());
+
+            let mut values = ImplicitTreap::<u32, SmallRng>::from_seed([chunk as u8; 32]);
+            values.extend(chunk * 100..(chunk + 1) * 100);
+            implicit.append(&mut values);
+            assert_eq!(values.iter().len(), 0);
+        }
+
+        assert!(treap.range::<u32, _>(..).copied().eq(0..1_000));
+        assert_eq!(treap.len(), 1_000);
+        assert_eq!(treap.kth(500), Some(&500));
+        assert_eq!(treap.check_invariants(), Ok(()));
+        assert_eq!(implicit, (0..1_000).collect::<Vec<u32>>());
+        assert_eq!(implicit.check_invariants(), Ok(()));
+
+        let mut overlapping = Treap::<u32, SmallRng>::from_seed([48; 32]);
+        overlapping.extend([5, 500, 2_000]);
+        treap.append(&mut overlapping);
+        assert!(overlapping.is_empty());
+        assert_eq!(treap.len(), 1_001);
+        assert_eq!(treap.last(), Some(&2_000));
+        assert_eq!(treap.check_invariants(), Ok(()));
+
+        implicit.insert_before(1_000, 7);
+        assert_eq!(implicit.get(1_000), Some(&7));
+    }
+
+    #[test]
+    fn extract_range_moves_subarray() {
+        let mut source = ImplicitTreap::<u32, SmallRng>::from_seed([49; 32]);
+        let mut target = ImplicitTreap::<u32, SmallRng>::from_seed([50; 32]);
+        source.extend(0..10);
+        target.extend(100..105);
+
+        let mut cut = source.extract_range(3, 7);
+        assert_eq!(cut, vec![3, 4, 5, 6]);
+        assert_eq!(source, vec![0, 1, 2, 7, 8, 9]);
+
+        let mut tail = target.split_off(2);
+        target.append(&mut cut);
+        target.append(&mut tail);
+        assert_eq!(target, vec![100, 101, 3, 4, 5, 6, 102, 103, 104]);
+        assert_eq!(target.check_invariants(), Ok(()));
+
+        assert_eq!(source.extract_range(2, 2), Vec::new());
+        assert_eq!(source, vec![0, 1, 2, 7, 8, 9]);
+
+        let mut keys = Treap::<u32, SmallRng>::from_seed([51; 32]);
+        keys.extend(0..20);
+        let mut moved = keys.extract_range(&5, &15);
+        assert!(moved.range::<u32, _>(..).copied().eq(5..15));
+        assert_eq!(keys.len(), 10);
+        assert!(keys.extract_range(&7, &7).is_empty());
+
+        let mut other = Treap::<u32, SmallRng>::from_seed([52; 32]);
+        other.extend(100..110);
+        moved.append(&mut other);
+        assert_eq!(moved.len(), 20);
+        assert_eq!(keys.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn implicit_treap_checked_access() {
+        let mut treap = ImplicitTreap::<u32, SmallRng>::from_seed([53; 32]);
+        treap.extend(0..5);
+
+        assert_eq!(treap.try_insert_before(5, 5), Ok(()));
+        assert_eq!(treap, vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(treap.try_insert_before(11, 6), Err(IndexError { index: 11, len: 6 }));
+        assert_eq!(treap, vec![0, 1, 2, 3, 4, 5]);
+
+        assert_eq!(treap.try_get(5), Ok(&5));
+        assert_eq!(treap.try_get(6), Err(IndexError { index: 6, len: 6 }));
+        assert_eq!(IndexError { index: 6, len: 6 }.to_string(), "index 6 is out of bounds for length 6");
+
+        treap.insert_before(6, 6);
+        assert_eq!(treap.get(6), Some(&6));
+        assert_eq!(treap.len(), 7);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    #[should_panic(expected = "insertion index (is 4) should be <= len (is 3)")]
+    fn implicit_treap_insert_out_of_bounds() {
+        let mut treap = ImplicitTreap::build(&[1, 2, 3]);
+        treap.insert_before(4, 4);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    #[should_panic(expected = "range end 4 is out of bounds for length 3")]
+    fn implicit_treap_remove_range_out_of_bounds() {
+        let mut treap = ImplicitTreap::build(&[1, 2, 3]);
+        treap.remove_range(1, 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "range end 20 is out of bounds for length 8")]
+    fn implicit_treap_reverse_range_out_of_bounds() {
+        let mut treap = ImplicitTreap::<i32, SmallRng>::from_seed([62; 32]);
+        treap.extend(0..8);
+        treap.reverse_range(6, 20);
+    }
+
+    #[test]
+    #[should_panic(expected = "range 6..2 is reversed")]
+    fn implicit_treap_reverse_range_reversed() {
+        let mut treap = ImplicitTreap::<i32, SmallRng>::from_seed([63; 32]);
+        treap.extend(0..8);
+        treap.reverse_range(6, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "range end 20 is out of bounds for length 8")]
+    fn implicit_treap_add_range_out_of_bounds() {
+        let mut treap = ImplicitTreap::<i32, SmallRng>::from_seed([62; 32]).with_lazy(|value, delta, len| value + delta * len as i32);
+        treap.extend(0..8);
+        treap.add_range(6, 20, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "range 6..2 is reversed")]
+    fn implicit_treap_add_range_reversed() {
+        let mut treap = ImplicitTreap::<i32, SmallRng>::from_seed([63; 32]).with_lazy(|value, delta, len| value + delta * len as i32);
+        treap.extend(0..8);
+        treap.add_range(6, 2, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "range end 20 is out of bounds for length 8")]
+    fn implicit_treap_query_range_out_of_bounds() {
+        let mut treap = ImplicitTreap::<i32, SmallRng>::from_seed([64; 32]).with_merge(|a, b| a + b);
+        treap.extend(0..8);
+        treap.query_range(6, 20);
+    }
+
+    #[test]
+    fn debug_output() {
+        let mut treap = Treap::with_rng(ScriptedRng(vec![5, 9, 2].into_iter()));
+        treap.extend([3, 1, 7]);
+        assert_eq!(format!("{treap:?}"), "{1, 3, 7}");
+        assert_eq!(format!("{treap:#?}"), "\
+{
+    Node {
+        key: 1,
+        size: 3,
+        priority: 9,
+    },
+    Node {
+        key: 3,
+        size: 2,
+        priority: 5,
+    },
+    Node {
+        key: 7,
+        size: 1,
+        priority: 2,
+    },
+}");
+        assert_eq!(format!("{:?}", Treap::<u8, SmallRng>::from_seed([0; 32])), "{}");
+
+        let mut implicit = ImplicitTreap::with_rng(ScriptedRng(vec![4, 8].into_iter()));
+        implicit.extend([5, 2]);
+        assert_eq!(format!("{implicit:?}"), "[5, 2]");
+        assert_eq!(format!("{implicit:#?}"), "\
+[
+    Node {
+        value: 5,
+        size: 1,
+        priority: 4,
+    },
+    Node {
+        value: 2,
+        size: 2,
+        priority: 8,
+    },
+]");
+
+        let mut lazy = ImplicitTreap::<i32, SmallRng>::from_seed([54; 32])
+            .with_lazy(|value, delta, _| value + delta);
+        lazy.extend(0..50);
+        lazy.add_range(10, 40, 100);
+        lazy.reverse_range(5, 45);
+        lazy.add_range(0, 20, 1_000);
+        let pending = format!("{lazy:?}");
+        lazy.flush();
+        assert_eq!(pending, format!("{:?}", lazy.to_vec()));
+    }
+
+    #[test]
+    fn implicit_treap_into_iter() {
+        #[derive(Debug, PartialEq)]
+        struct Boxed(Box<u32>);
+
+        let mut treap = ImplicitTreap::<Boxed, SmallRng>::from_seed([57; 32]);
+        treap.extend((0..100).map(|value| Boxed(Box::new(value))));
+        treap.reverse_range(10, 90);
+        treap.remove(0);
+
+        let mut values = treap.into_iter();
+        assert_eq!(values.len(), 99);
+        assert_eq!(values.next(), Some(Boxed(Box::new(1))));
+        assert_eq!(values.nth(8), Some(Boxed(Box::new(89))));
+        assert_eq!(values.len(), 89);
+
+        let mut expected: Vec<u32> = (0..100).collect();
+        expected[10..90].reverse();
+        let rest: Vec<u32> = values.map(|boxed| *boxed.0).collect();
+        assert_eq!(rest, expected[11..]);
+
+        let worker = std::thread::Builder::new()
+            .stack_size(256 * 1024)
+            .spawn(|| {
+                const N: usize = 1_000_000;
+
+                let values: Vec<u32> = (0..N as u32).collect();
+                let mut treap = ImplicitTreap::<u32, SmallRng>::from_seed([58; 32])
+                    .with_lazy(|value, delta, _| value + delta);
+                treap.init_with(&values);
+                treap.add_range(0, N / 2, 1);
+
+                let owned: Vec<u32> = treap.into_iter().collect();
+                assert_eq!(owned.len(), N);
+                assert!(owned.iter().enumerate().all(|(index, &value)| value == index as u32 + u32::from(index < N / 2)));
+            })
+            .unwrap();
+
+        worker.join().unwrap();
+    }
+
+    #[test]
+    fn implicit_treap_iter_mut() {
+        let mut treap = ImplicitTreap::<i64, SmallRng>::from_seed([56; 32])
+            .with_merge(|a, b| a + b)
+            .with_lazy(|aggregate, delta, len| aggregate + delta * len as i64);
+        let mut values: Vec<i64> = (0..1_000).collect();
+        treap.extend(values.iter().copied());
+
+        treap.reverse_range(100, 900);
+        values[100..900].reverse();
+        treap.add_range(0, 500, 7);
+        values[..500].iter_mut().for_each(|value| *value += 7);
+
+        assert_eq!(treap.iter_mut().len(), 1_000);
+        for value in &mut treap {
+            *value *= 2;
+        }
+
+        assert_eq!(treap.query_range(0, 1_000), values.iter().sum::<i64>() * 2);
+        assert_eq!(treap.query_range(250, 750), values[250..750].iter().sum::<i64>() * 2);
+
+        for (index, value) in values.iter().enumerate() {
+            assert_eq!(treap.get(index), Some(&(value * 2)), "index: {index}");
+        }
+        assert_eq!(treap.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn implicit_treap_into_split() {
+        let values: Vec<u32> = (0..50).map(|value| value * 3 % 50).collect();
+
+        for index in [0, 1, 25, 49, 50] {
+            let mut treap = ImplicitTreap::<u32, SmallRng>::from_seed([37; 32]);
+            treap.init_with(&values);
+
+            let (mut less, mut greater) = treap.into_split(index);
+            assert_eq!(less.to_vec(), values[..index]);
+            assert_eq!(greater.to_vec(), values[index..]);
+
+            less.append(&mut greater);
+            assert_eq!(less.to_vec(), values);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    #[should_panic]
+    fn implicit_treap_split_off_out_of_bounds() {
+        let mut treap = ImplicitTreap::build(&[1, 2, 3]);
+        treap.split_off(4);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    #[should_panic(expected = "split index (is 6) should be <= len (is 5)")]
+    fn implicit_treap_split_out_of_bounds() {
+        let mut treap = ImplicitTreap::build(&[1, 2, 3, 4, 5]);
+        treap.split(6);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    #[should_panic(expected = "range end 100 is out of bounds for length 5")]
+    fn implicit_treap_drain_out_of_bounds() {
+        let mut treap = ImplicitTreap::build(&[1, 2, 3, 4, 5]);
+        let _ = treap.drain(2, 100);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    #[should_panic(expected = "range end 7 is out of bounds for length 5")]
+    fn implicit_treap_extract_range_out_of_bounds() {
+        let mut treap = ImplicitTreap::build(&[1, 2, 3, 4, 5]);
+        treap.extract_range(0, 7);
+    }
+
+    /// Hashes keys, priorities and shape in pre-order
+    fn hash_structure<K: Ord + Hash>(node: &Link<K>, hasher: &mut DefaultHasher) {
+        match node {
+            None => false.hash(hasher),
+            Some(node) => {
+                (true, &node.key, node.priority).hash(hasher);
+                hash_structure(&node.left, hasher);
+                hash_structure(&node.right, hasher);
+            }
+        }
+    }
+
+    fn hash_implicit_structure<T: Hash>(node: &ImplicitLink<T>, hasher: &mut DefaultHasher) {
+        match node {
+            None => false.hash(hasher),
+            Some(node) => {
+                (true, &node.value, node.priority, node.size).hash(hasher);
+                hash_implicit_structure(&node.left, hasher);
+                hash_implicit_structure(&node.right, hasher);
+            }
+        }
+    }
+
+    /// Hashes keys and shape in pre-order, but not priorities
+    fn hash_shape<K: Ord + Hash>(node: &Link<K>, hasher: &mut DefaultHasher) {
+        match node {
+            None => false.hash(hasher),
+            Some(node) => {
+                (true, &node.key).hash(hasher);
+                hash_shape(&node.left, hasher);
+                hash_shape(&node.right, hasher);
+            }
+        }
+    }
+
+    #[test]
+    fn hashed_priorities_shape() {
+        use std::hash::BuildHasherDefault;
+
+        let mut rng = SmallRng::seed_from_u64(1);
+        let mut keys: Vec<u32> = (0..2_000).map(|_| rng.gen_range(0..10_000)).collect();
+        let hasher = BuildHasherDefault::<DefaultHasher>::default();
+
+        let mut first = Treap::with_hashed_priorities(hasher.clone());
+        first.extend(keys.iter().copied());
+        keys.shuffle(&mut rng);
+        let mut second = Treap::with_hashed_priorities(hasher.clone());
+        second.extend(keys.iter().copied());
+        assert_treap_invariants(&second.root);
+        assert_eq!(hash_of(&first.root), hash_of(&second.root));
+
+        let greater = second.split(&5_000);
+        second = merge(second, greater);
+        second.remove(&keys[0]);
+        second.insert(keys[0]);
+        assert_eq!(hash_of(&first.root), hash_of(&second.root));
+
+        let mut sorted = keys.clone();
+        sorted.sort();
+        let mut third = Treap::with_hashed_priorities(hasher);
+        third.init_with_sorted(sorted);
+        assert_eq!(hash_of(&first.root), hash_of(&third.root));
+    }
+
+    #[test]
+    fn clone_reseeds_rng() {
+        let mut treap = Treap::<u32, SmallRng>::from_seed([40; 32]);
+        treap.extend(0..100);
+        let mut copy = treap.clone();
+        assert!(copy.range::<u32, _>(..).eq(treap.range::<u32, _>(..)));
+        assert_eq!(hash_of(&copy.root), hash_of(&treap.root));
+
+        treap.extend(100..2_000);
+        copy.extend(100..2_000);
+        let shapes = [&treap, &copy].map(|treap| {
+            let mut hasher = DefaultHasher::new();
+            hash_shape(&treap.root, &mut hasher);
+            hasher.finish()
+        });
+        assert_ne!(shapes[0], shapes[1]);
+
+        let mut implicit = ImplicitTreap::<u32, SmallRng>::from_seed([41; 32]);
+        implicit.extend(0..100);
+        let mut copy = implicit.clone();
+        implicit.extend(100..2_000);
+        copy.extend(100..2_000);
+        assert_eq!(copy.to_vec(), implicit.to_vec());
+
+        let hashes = [&implicit, &copy].map(|treap| {
+            let mut hasher = DefaultHasher::new();
+            hash_implicit_structure(&treap.root, &mut hasher);
+            hasher.finish()
+        });
+        assert_ne!(hashes[0], hashes[1]);
+    }
+
+    #[test]
+    fn treaps_compare_contents() {
+        let mut first = Treap::<u32, SmallRng>::from_seed([42; 32]);
+        let mut second = Treap::<u32, SmallRng>::from_seed([43; 32]);
+        first.extend(0..300);
+        second.extend((0..300).rev());
+        assert_eq!(first, second);
+
+        second.remove(&150);
+        assert_ne!(first, second);
+        second.insert(1_000);
+        assert_ne!(first, second);
+
+        let mut first = ImplicitTreap::<u32, SmallRng>::from_seed([44; 32]);
+        let mut second = ImplicitTreap::<u32, SmallRng>::from_seed([45; 32]);
+        first.extend(0..300);
+        for value in (0..300).rev() {
+            second.insert_before(0, value);
+        }
+        assert_eq!(first, second);
+        assert_eq!(first, (0..300).collect::<Vec<u32>>());
+        assert_eq!(first, &(0..300).collect::<Vec<u32>>()[..]);
+
+        *second.get_mut(150).unwrap() = 0;
+        assert_ne!(first, second);
+        second.remove(150);
+        assert_ne!(first, second);
+        assert_ne!(first, vec![0, 1, 2]);
+
+        let lazy = |value: &u32, delta: &u32, len| value + delta * len as u32;
+        let mut first = ImplicitTreap::<u32, SmallRng>::from_seed([46; 32]).with_lazy(lazy);
+        let mut second = ImplicitTreap::<u32, SmallRng>::from_seed([47; 32]).with_lazy(lazy);
+        first.extend(0..300);
+        second.extend(0..300);
+        first.add_range(0, 300, 1);
+        second.add_range(0, 150, 1);
+        second.reverse_range(150, 300);
+        second.add_range(150, 300, 1);
+        second.reverse_range(150, 300);
+        assert_eq!(first, second);
+        assert_eq!(first, (1..301).collect::<Vec<u32>>());
+        assert_ne!(first, (0..300).collect::<Vec<u32>>());
+        second.add_range(299, 300, 1);
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn treaps_with_same_rng_are_identical() {
+        let mut first = Treap::with_rng(StdRng::seed_from_u64(19));
+        let mut second = Treap::with_rng(StdRng::seed_from_u64(19));
+        let mut first_implicit = ImplicitTreap::with_rng(StdRng::seed_from_u64(19));
+        let mut second_implicit = ImplicitTreap::with_rng(StdRng::seed_from_u64(19));
+
+        let mut rng = SmallRng::seed_from_u64(19);
+        for len in 0..500 {
+            let value: u32 = rng.gen();
+            let index = rng.gen_range(0..=len);
+
+            for treap in [&mut first, &mut second] {
+                treap.insert(value);
+            }
+            for treap in [&mut first_implicit, &mut second_implicit] {
+                treap.insert_before(index, value);
+            }
+        }
+
+        let hashes = [&first, &second].map(|treap| {
+            let mut hasher = DefaultHasher::new();
+            hash_structure(&treap.root, &mut hasher);
+            hasher.finish()
+        });
+        assert_eq!(hashes[0], hashes[1]);
+
+        let hashes = [&first_implicit, &second_implicit].map(|treap| {
+            let mut hasher = DefaultHasher::new();
+            hash_implicit_structure(&treap.root, &mut hasher);
+            hasher.finish()
+        });
+        assert_eq!(hashes[0], hashes[1]);
+        assert_eq!(first_implicit.to_vec(), second_implicit.to_vec());
+    }
+}
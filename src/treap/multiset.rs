@@ -0,0 +1,411 @@
+use rand::rngs::SmallRng;
+use rand::{SeedableRng, RngCore};
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use core::borrow::Borrow;
+use core::cmp::Ordering;
+
+use super::ForkSource;
+
+/// Ordered multiset, copies of a key share one node with their count
+#[derive(Debug)]
+pub struct TreapMultiset<K: Ord, R = SmallRng> {
+    root: MultisetLink<K>,
+    rng: R,
+}
+
+#[derive(Clone, Debug)]
+struct MultisetNode<K> {
+    key: K,
+    /// Copies of key
+    count: usize,
+    /// Copies of every key in the subtree
+    total: usize,
+    priority: u64,
+    left: MultisetLink<K>,
+    right: MultisetLink<K>,
+}
+
+type MultisetLink<K> = Option<Box<MultisetNode<K>>>;
+
+/// Distinct keys of a TreapMultiset in order, with their counts
+pub struct MultisetIter<'a, K: Ord> {
+    stack: Vec<&'a MultisetNode<K>>,
+}
+
+#[cfg(feature = "std")]
+impl<K: Ord> TreapMultiset<K, SmallRng> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K: Ord, R: SeedableRng> Default for TreapMultiset<K, R> {
+    fn default() -> Self {
+        let rng = R::from_entropy();
+        Self {
+            root: None,
+            rng,
+        }
+    }
+}
+
+impl<K: Ord, R: SeedableRng + RngCore> TreapMultiset<K, R> {
+    pub fn from_seed(seed: R::Seed) -> Self {
+        let rng = R::from_seed(seed);
+        Self {
+            root: None,
+            rng,
+        }
+    }
+
+    /// Bumps the count of key in a single descent
+    pub fn insert(&mut self, key: K) {
+        let priority = self.rng.next_u64();
+        insert_copy(&mut self.root, key, priority);
+    }
+
+    /// Removes one copy of key, returns whether there was one
+    pub fn remove_one<Q: Ord + ?Sized>(&mut self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+    {
+        remove_copies(&mut self.root, key, false) > 0
+    }
+
+    /// Removes every copy of key, returns how many there were
+    pub fn remove_all<Q: Ord + ?Sized>(&mut self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+    {
+        remove_copies(&mut self.root, key, true)
+    }
+
+    pub fn count<Q: Ord + ?Sized>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+    {
+        let mut node = self.root.as_deref();
+        while let Some(nd) = node {
+            match key.cmp(nd.key.borrow()) {
+                Ordering::Less => node = nd.left.as_deref(),
+                Ordering::Greater => node = nd.right.as_deref(),
+                Ordering::Equal => return nd.count,
+            }
+        }
+
+        0
+    }
+
+    /// Number of elements less than key, counting every copy, O(height)
+    pub fn rank<Q: Ord + ?Sized>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+    {
+        let mut res = 0;
+        let mut node = self.root.as_deref();
+        while let Some(nd) = node {
+            if nd.key.borrow() < key {
+                res += node_total(&nd.left) + nd.count;
+                node = nd.right.as_deref();
+            } else {
+                node = nd.left.as_deref();
+            }
+        }
+
+        res
+    }
+
+    #[inline]
+    pub fn contains<Q: Ord + ?Sized>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+    {
+        self.count(key) > 0
+    }
+
+    /// Number of elements, counting every copy
+    #[inline]
+    pub fn len(&self) -> usize {
+        node_total(&self.root)
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    pub fn iter(&self) -> MultisetIter<'_, K> {
+        let mut res = MultisetIter {
+            stack: Vec::new(),
+        };
+
+        res.push_left(self.root.as_deref());
+        res
+    }
+}
+
+/// The copy gets an rng forked from a copy of the rng of self,
+/// so both multisets do not draw the same priorities afterwards
+impl<K: Ord + Clone, R: Clone + ForkSource> Clone for TreapMultiset<K, R> {
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+            rng: self.rng.clone().fork(),
+        }
+    }
+}
+
+impl<'a, K: Ord, R: SeedableRng + RngCore> IntoIterator for &'a TreapMultiset<K, R> {
+    type Item = (&'a K, usize);
+    type IntoIter = MultisetIter<'a, K>;
+
+    fn into_iter(self) -> MultisetIter<'a, K> {
+        self.iter()
+    }
+}
+
+impl<'a, K: Ord> MultisetIter<'a, K> {
+    fn push_left(&mut self, mut node: Option<&'a MultisetNode<K>>) {
+        while let Some(nd) = node {
+            self.stack.push(nd);
+            node = nd.left.as_deref();
+        }
+    }
+}
+
+impl<'a, K: Ord> Iterator for MultisetIter<'a, K> {
+    type Item = (&'a K, usize);
+
+    fn next(&mut self) -> Option<(&'a K, usize)> {
+        let node = self.stack.pop()?;
+        self.push_left(node.right.as_deref());
+        Some((&node.key, node.count))
+    }
+}
+
+fn node_total<K>(node: &MultisetLink<K>) -> usize {
+    node.as_ref().map_or(0, |node| node.total)
+}
+
+/// Same as insert_node, but an existing key below the new priority takes
+/// that priority and moves up, so the path is walked once
+fn insert_copy<K: Ord>(link: &mut MultisetLink<K>, key: K, priority: u64) {
+    if link.as_ref().is_some_and(|node| node.priority >= priority) {
+        let node = link.as_mut().unwrap();
+        node.total += 1;
+
+        match key.cmp(&node.key) {
+            Ordering::Less => insert_copy(&mut node.left, key, priority),
+            Ordering::Greater => insert_copy(&mut node.right, key, priority),
+            Ordering::Equal => node.count += 1,
+        }
+
+        return;
+    }
+
+    let (less, equal, greater) = split_multiset_node(link.take(), &key);
+    let mut node = match equal {
+        Some(mut node) => {
+            node.count += 1;
+            node
+        }
+        None => Box::new(MultisetNode::new(key, priority)),
+    };
+
+    node.priority = priority;
+    node.left = less;
+    node.right = greater;
+    node.update();
+    *link = Some(node);
+}
+
+/// Removes one copy of key, or every copy if all, returns how many were removed
+fn remove_copies<K: Borrow<Q>, Q: Ord + ?Sized>(link: &mut MultisetLink<K>, key: &Q, all: bool) -> usize {
+    let Some(node) = link.as_mut() else {
+        return 0;
+    };
+
+    let removed = match key.cmp(node.key.borrow()) {
+        Ordering::Less => remove_copies(&mut node.left, key, all),
+        Ordering::Greater => remove_copies(&mut node.right, key, all),
+        Ordering::Equal if !all && node.count > 1 => {
+            node.count -= 1;
+            1
+        }
+        Ordering::Equal => {
+            let node = link.take().unwrap();
+            *link = merge_multiset_nodes(node.left, node.right);
+            return node.count;
+        }
+    };
+
+    node.total -= removed;
+    removed
+}
+
+fn merge_multiset_nodes<K>(left: MultisetLink<K>, right: MultisetLink<K>) -> MultisetLink<K> {
+    match (left, right) {
+        (None, rest) | (rest, None) => rest,
+        (Some(mut left), Some(mut right)) => {
+            if left.priority > right.priority {
+                left.right = merge_multiset_nodes(left.right.take(), Some(right));
+                left.update();
+                Some(left)
+            } else {
+                right.left = merge_multiset_nodes(Some(left), right.left.take());
+                right.update();
+                Some(right)
+            }
+        }
+    }
+}
+
+/// Splits into keys less than key, the node of key and keys greater than key
+fn split_multiset_node<K: Ord>(node: MultisetLink<K>, key: &K) 
+-> (MultisetLink<K>, MultisetLink<K>, MultisetLink<K>) {
+    let Some(mut node) = node else {
+        return (None, None, None);
+    };
+
+    match node.key.cmp(key) {
+        Ordering::Less => {
+            let (less, equal, greater) = split_multiset_node(node.right.take(), key);
+            node.right = less;
+            node.update();
+            (Some(node), equal, greater)
+        }
+        Ordering::Greater => {
+            let (less, equal, greater) = split_multiset_node(node.left.take(), key);
+            node.left = greater;
+            node.update();
+            (less, equal, Some(node))
+        }
+        Ordering::Equal => {
+            let (less, greater) = (node.left.take(), node.right.take());
+            node.update();
+            (less, Some(node), greater)
+        }
+    }
+}
+
+impl<K> MultisetNode<K> {
+    fn new(key: K, priority: u64) -> Self {
+        Self {
+            key,
+            count: 1,
+            total: 1,
+            priority,
+            left: None,
+            right: None,
+        }
+    }
+
+    fn update(&mut self) {
+        self.total = self.count + node_total(&self.left) + node_total(&self.right);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn treap_multiset() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let mut multiset = TreapMultiset::<u8, SmallRng>::from_seed([6; 32]);
+        let mut expected: BTreeMap<u8, usize> = BTreeMap::new();
+
+        for _ in 0..5_000 {
+            let key = rng.gen_range(0..30);
+
+            match rng.gen_range(0..5) {
+                0..=2 => {
+                    multiset.insert(key);
+                    *expected.entry(key).or_insert(0) += 1;
+                }
+                3 => {
+                    let removed = expected.contains_key(&key);
+                    if expected.get(&key) == Some(&1) {
+                        expected.remove(&key);
+                    } else if let Some(count) = expected.get_mut(&key) {
+                        *count -= 1;
+                    }
+                    assert_eq!(multiset.remove_one(&key), removed, "key: {key}");
+                }
+                _ => assert_eq!(multiset.remove_all(&key), expected.remove(&key).unwrap_or(0), "key: {key}"),
+            }
+
+            assert_eq!(multiset.count(&key), expected.get(&key).copied().unwrap_or(0));
+            assert_eq!(multiset.len(), expected.values().sum::<usize>());
+            assert_eq!(multiset.rank(&key), expected.range(..key).map(|(_, &count)| count).sum::<usize>(), "key: {key}");
+        }
+
+        assert!(multiset.iter().eq(expected.iter().map(|(key, &count)| (key, count))));
+    }
+
+    #[test]
+    fn treap_multiset_duplicates() {
+        let mut multiset = TreapMultiset::<&str, SmallRng>::from_seed([11; 32]);
+        for key in ["b", "a", "b", "c", "b", "a"] {
+            multiset.insert(key);
+        }
+
+        assert_eq!(multiset.len(), 6);
+        assert_eq!(multiset.count("a"), 2);
+        assert_eq!(multiset.count("b"), 3);
+        assert_eq!(multiset.count("d"), 0);
+        assert_eq!(multiset.iter().count(), 3);
+
+        assert_eq!(multiset.rank("a"), 0);
+        assert_eq!(multiset.rank("b"), 2);
+        assert_eq!(multiset.rank("c"), 5);
+        assert_eq!(multiset.rank("z"), 6);
+
+        assert!(multiset.remove_one("b"));
+        assert_eq!(multiset.rank("c"), 4);
+        assert_eq!(multiset.len(), 5);
+    }
+
+    #[test]
+    fn treap_multiset_clone_draws_other_priorities() {
+        let mut multiset = TreapMultiset::<u32, SmallRng>::from_seed([13; 32]);
+        multiset.insert(0);
+        let mut copy = multiset.clone();
+
+        for key in 1..100 {
+            multiset.insert(key);
+            copy.insert(key);
+        }
+
+        assert!(multiset.iter().eq(copy.iter()));
+        assert_ne!(
+            multiset.root.as_ref().map(|root| root.priority),
+            copy.root.as_ref().map(|root| root.priority)
+        );
+    }
+
+    #[test]
+    fn treap_multiset_heap_order() {
+        let mut rng = SmallRng::seed_from_u64(7);
+        let mut multiset = TreapMultiset::<u8, SmallRng>::from_seed([12; 32]);
+        for _ in 0..2_000 {
+            multiset.insert(rng.gen_range(0..50));
+        }
+
+        let mut stack: Vec<&MultisetNode<u8>> = multiset.root.as_deref().into_iter().collect();
+        while let Some(node) = stack.pop() {
+            assert_eq!(node.total, node.count + node_total(&node.left) + node_total(&node.right));
+            for child in [&node.left, &node.right].into_iter().flatten() {
+                assert!(child.priority <= node.priority);
+                stack.push(child);
+            }
+        }
+        assert_eq!(multiset.len(), 2_000);
+    }
+}
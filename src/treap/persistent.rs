@@ -0,0 +1,262 @@
+use rand::rngs::SmallRng;
+use rand::{SeedableRng, RngCore};
+
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+
+use super::ForkSource;
+
+/// Implicit treap whose nodes are shared between copies, so clone is O(1)
+/// and every edit copies only the O(log n) nodes on its path
+///
+/// T needs Clone, because a shared node is copied before it is changed
+#[derive(Debug)]
+pub struct PersistentImplicitTreap<T: Clone, R = SmallRng> {
+    root: PersistentLink<T>,
+    rng: R,
+}
+
+#[derive(Clone, Debug)]
+struct PersistentNode<T> {
+    value: T,
+    size: usize,
+    priority: u64,
+    left: PersistentLink<T>,
+    right: PersistentLink<T>,
+}
+
+type PersistentLink<T> = Option<Rc<PersistentNode<T>>>;
+
+#[cfg(feature = "std")]
+impl<T: Clone> PersistentImplicitTreap<T, SmallRng> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: Clone, R: SeedableRng> Default for PersistentImplicitTreap<T, R> {
+    fn default() -> Self {
+        let rng = R::from_entropy();
+        Self {
+            root: None,
+            rng,
+        }
+    }
+}
+
+impl<T: Clone, R: SeedableRng + RngCore> PersistentImplicitTreap<T, R> {
+    pub fn from_seed(seed: R::Seed) -> Self {
+        let rng = R::from_seed(seed);
+        Self {
+            root: None,
+            rng,
+        }
+    }
+
+    /// Cheap copy sharing every node with self, see Clone for its rng
+    #[inline]
+    pub fn snapshot(&self) -> Self
+    where
+        R: Clone,
+    {
+        self.clone()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        let mut node = self.root.as_deref();
+        let mut index = index;
+
+        while let Some(nd) = node {
+            let left_size = node_size(&nd.left);
+
+            if index < left_size {
+                node = nd.left.as_deref();
+            } else if index == left_size {
+                return Some(&nd.value);
+            } else {
+                index -= left_size + 1;
+                node = nd.right.as_deref();
+            }
+        }
+
+        None
+    }
+
+    /// Inserts value so that it ends up at index
+    pub fn insert_before(&mut self, index: usize, value: T) {
+        assert!(index <= self.len(), "index {index} is out of range for length {}", self.len());
+
+        let (less, greater) = split_persistent_node(self.root.take(), index);
+        let node = PersistentNode::new(value, self.rng.next_u64());
+        let less = merge_persistent_nodes(less, Some(Rc::new(node)));
+        self.root = merge_persistent_nodes(less, greater);
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<T> {
+        let (less, greater) = split_persistent_node(self.root.take(), index);
+        let (middle, greater) = split_persistent_node(greater, 1);
+        self.root = merge_persistent_nodes(less, greater);
+        middle.map(|node| node.value.clone())
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        node_size(&self.root)
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    pub fn to_vec(&self) -> Vec<T> {
+        let mut res = Vec::with_capacity(self.len());
+        let mut stack = Vec::new();
+        let mut node = self.root.as_deref();
+
+        while node.is_some() || !stack.is_empty() {
+            while let Some(nd) = node {
+                stack.push(nd);
+                node = nd.left.as_deref();
+            }
+
+            let nd = stack.pop().unwrap();
+            res.push(nd.value.clone());
+            node = nd.right.as_deref();
+        }
+
+        res
+    }
+}
+
+/// The copy gets an rng forked from a copy of the rng of self,
+/// so both versions do not draw the same priorities afterwards
+impl<T: Clone, R: Clone + ForkSource> Clone for PersistentImplicitTreap<T, R> {
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+            rng: self.rng.clone().fork(),
+        }
+    }
+}
+
+fn node_size<T>(node: &PersistentLink<T>) -> usize {
+    node.as_ref().map_or(0, |node| node.size)
+}
+
+/// Rc::make_mut copies a node only if some other version still uses it
+fn merge_persistent_nodes<T: Clone>(left: PersistentLink<T>, right: PersistentLink<T>)
+-> PersistentLink<T> {
+    match (left, right) {
+        (None, right) => right,
+        (left, None) => left,
+        (Some(mut left), Some(mut right)) => {
+            if left.priority > right.priority {
+                let node = Rc::make_mut(&mut left);
+                node.right = merge_persistent_nodes(node.right.take(), Some(right));
+                node.update();
+                Some(left)
+            } else {
+                let node = Rc::make_mut(&mut right);
+                node.left = merge_persistent_nodes(Some(left), node.left.take());
+                node.update();
+                Some(right)
+            }
+        }
+    }
+}
+
+fn split_persistent_node<T: Clone>(node: PersistentLink<T>, index: usize)
+-> (PersistentLink<T>, PersistentLink<T>) {
+    match node {
+        None => (None, None),
+        Some(mut rc) => {
+            let node = Rc::make_mut(&mut rc);
+            let left_size = node_size(&node.left);
+
+            if left_size < index {
+                let (l, r) = split_persistent_node(node.right.take(), index - left_size - 1);
+                node.right = l;
+                node.update();
+                (Some(rc), r)
+            } else {
+                let (l, r) = split_persistent_node(node.left.take(), index);
+                node.left = r;
+                node.update();
+                (l, Some(rc))
+            }
+        }
+    }
+}
+
+impl<T> PersistentNode<T> {
+    fn new(value: T, priority: u64) -> Self {
+        Self {
+            value,
+            size: 1,
+            priority,
+            left: None,
+            right: None,
+        }
+    }
+
+    fn update(&mut self) {
+        self.size = 1 + node_size(&self.left) + node_size(&self.right);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn snapshots_stay_unchanged() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let mut treap = PersistentImplicitTreap::<u32, SmallRng>::from_seed([5; 32]);
+        let mut values = Vec::new();
+        let mut history = Vec::new();
+
+        for step in 0..500 {
+            if !values.is_empty() && rng.gen_bool(0.3) {
+                let index = rng.gen_range(0..values.len());
+                assert_eq!(treap.remove(index), Some(values.remove(index)));
+            } else {
+                let index = rng.gen_range(0..=values.len());
+                treap.insert_before(index, step);
+                values.insert(index, step);
+            }
+
+            if step % 50 == 0 {
+                history.push((treap.snapshot(), values.clone()));
+            }
+        }
+
+        assert_eq!(treap.to_vec(), values);
+
+        for (snapshot, values) in history {
+            assert_eq!(snapshot.to_vec(), values);
+            assert_eq!(snapshot.len(), values.len());
+
+            for (index, value) in values.iter().enumerate() {
+                assert_eq!(snapshot.get(index), Some(value));
+            }
+        }
+    }
+
+    #[test]
+    fn snapshot_draws_other_priorities() {
+        let mut treap = PersistentImplicitTreap::<u32, SmallRng>::from_seed([9; 32]);
+        treap.insert_before(0, 0);
+        let mut snapshot = treap.snapshot();
+
+        for value in 1..100 {
+            treap.insert_before(0, value);
+            snapshot.insert_before(0, value);
+        }
+
+        assert_eq!(treap.to_vec(), snapshot.to_vec());
+        assert_ne!(treap.root.as_ref().map(|root| root.priority), snapshot.root.as_ref().map(|root| root.priority));
+    }
+}
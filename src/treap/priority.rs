@@ -0,0 +1,56 @@
+use rand::{SeedableRng, RngCore};
+
+use core::hash::{BuildHasher, Hash};
+
+use super::fork_rng;
+
+/// Priorities of new nodes, every rng draws them at random
+pub trait PrioritySource<K: ?Sized> {
+    fn priority(&mut self, key: &K) -> u64;
+}
+
+/// Gives the other half of a split its own priorities
+pub trait ForkSource {
+    fn fork(&mut self) -> Self;
+}
+
+/// Priority of a key is its hash, so the shape of a treap only depends
+/// on its keys, unless two of them share a priority
+#[derive(Clone, Debug, Default)]
+pub struct HashedPriorities<H> {
+    hasher: H,
+}
+
+impl<K: ?Sized, R: RngCore> PrioritySource<K> for R {
+    #[inline]
+    fn priority(&mut self, _key: &K) -> u64 {
+        self.next_u64()
+    }
+}
+
+impl<R: SeedableRng + RngCore> ForkSource for R {
+    #[inline]
+    fn fork(&mut self) -> Self {
+        fork_rng(self)
+    }
+}
+
+impl<H: BuildHasher> HashedPriorities<H> {
+    pub fn new(hasher: H) -> Self {
+        Self { hasher }
+    }
+}
+
+impl<K: Hash + ?Sized, H: BuildHasher> PrioritySource<K> for HashedPriorities<H> {
+    #[inline]
+    fn priority(&mut self, key: &K) -> u64 {
+        self.hasher.hash_one(key)
+    }
+}
+
+impl<H: Clone> ForkSource for HashedPriorities<H> {
+    #[inline]
+    fn fork(&mut self) -> Self {
+        self.clone()
+    }
+}